@@ -60,6 +60,11 @@ impl AudioRecorder {
     }
 
     pub fn stop(&self) -> Vec<f32> {
+        self.drain()
+    }
+
+    /// Take the audio captured so far; recording continues if the stream is live
+    pub fn drain(&self) -> Vec<f32> {
         if let Ok(mut b) = self.buffer.lock() {
             b.drain(..).collect()
         } else {
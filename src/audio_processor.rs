@@ -1,11 +1,25 @@
-/// Audio processor for improving Whisper transcription quality.
-/// Implements chunking, silence trimming, and normalization.
+//! Audio processor for improving Whisper transcription quality.
+//! Implements chunking, silence trimming, and normalization.
 
-const SAMPLE_RATE: usize = 16_000;
+use std::ops::Range;
+
+pub const SAMPLE_RATE: usize = 16_000;
+
+/// Whisper's fixed input window in seconds. whisper.cpp zero-pads shorter
+/// input up to this length, so every chunk costs a full window to encode.
+pub const WHISPER_WINDOW_SECS: f32 = 30.0;
+
+/// A chunk of processed audio ready for Whisper
+pub struct AudioChunk {
+    pub samples: Vec<f32>,
+    /// Start of the chunk in the original recording, in seconds
+    pub offset_secs: f32,
+}
 
 /// Configuration for audio processing
 pub struct AudioProcessor {
-    /// Duration of each chunk in seconds (default: 25s - optimal for Whisper)
+    /// Maximum duration of each chunk in seconds, capped at the Whisper
+    /// window (default: 30s)
     pub chunk_duration_secs: f32,
     /// Overlap between chunks in seconds (default: 2s)
     pub overlap_secs: f32,
@@ -18,7 +32,7 @@ pub struct AudioProcessor {
 impl Default for AudioProcessor {
     fn default() -> Self {
         Self {
-            chunk_duration_secs: 25.0,
+            chunk_duration_secs: WHISPER_WINDOW_SECS,
             overlap_secs: 2.0,
             silence_threshold_db: -30.0,
             min_chunk_secs: 1.0,
@@ -49,17 +63,17 @@ impl AudioProcessor {
         10.0_f32.powf(db / 20.0)
     }
 
-    /// Trim silence from the beginning and end of audio
-    fn trim_silence(&self, audio: &[f32]) -> Vec<f32> {
+    /// Find the range of audio left after trimming leading and trailing silence
+    fn trim_silence(&self, audio: &[f32]) -> Range<usize> {
         if audio.is_empty() {
-            return Vec::new();
+            return 0..0;
         }
 
         let threshold = Self::db_to_linear(self.silence_threshold_db);
         let frame_size = SAMPLE_RATE / 100; // 10ms frames
 
-        // Find start (first frame above threshold)
-        let mut start = 0;
+        // Find start (first frame above threshold); none means all silence
+        let mut start = audio.len();
         for i in (0..audio.len()).step_by(frame_size) {
             let end = (i + frame_size).min(audio.len());
             let rms = Self::calculate_rms(&audio[i..end]);
@@ -81,10 +95,28 @@ impl AudioProcessor {
         }
 
         if start >= end {
-            return Vec::new();
+            return 0..0;
         }
 
-        audio[start..end].to_vec()
+        start..end
+    }
+
+    /// Check whether audio contains anything above the silence threshold
+    pub fn has_speech(&self, audio: &[f32]) -> bool {
+        let threshold = Self::db_to_linear(self.silence_threshold_db);
+        audio
+            .chunks(SAMPLE_RATE / 100)
+            .any(|frame| Self::calculate_rms(frame) > threshold)
+    }
+
+    /// Check whether the last `pause_secs` of audio are silent
+    pub fn ends_with_pause(&self, audio: &[f32], pause_secs: f32) -> bool {
+        let pause = (pause_secs * SAMPLE_RATE as f32) as usize;
+        if pause == 0 || audio.len() < pause {
+            return false;
+        }
+        let tail = &audio[audio.len() - pause..];
+        Self::rms_to_db(Self::calculate_rms(tail)) < self.silence_threshold_db
     }
 
     /// Normalize audio to [-1.0, 1.0] range
@@ -106,58 +138,68 @@ impl AudioProcessor {
         audio.iter().map(|s| s * scale).collect()
     }
 
-    /// Split audio into chunks with overlap
-    fn chunk_with_overlap(&self, audio: &[f32]) -> Vec<Vec<f32>> {
-        let chunk_samples = (self.chunk_duration_secs * SAMPLE_RATE as f32) as usize;
-        let overlap_samples = (self.overlap_secs * SAMPLE_RATE as f32) as usize;
+    /// Chunk length in samples, never larger than the Whisper window
+    fn window_samples(&self) -> usize {
+        let secs = self.chunk_duration_secs.min(WHISPER_WINDOW_SECS);
+        (secs * SAMPLE_RATE as f32) as usize
+    }
+
+    /// Split audio into overlapping chunk ranges.
+    ///
+    /// Chunks are balanced: audio is split into the fewest windows that fit,
+    /// and the length is spread evenly across them. Since Whisper pads each
+    /// chunk to a full window anyway, this avoids paying for a short tail chunk.
+    fn chunk_with_overlap(&self, audio: &[f32]) -> Vec<Range<usize>> {
+        let window = self.window_samples();
         let min_samples = (self.min_chunk_secs * SAMPLE_RATE as f32) as usize;
 
-        // If audio is shorter than chunk size, return as single chunk
-        if audio.len() <= chunk_samples {
+        // If audio fits in one window, return as single chunk
+        if audio.len() <= window {
             if audio.len() >= min_samples {
-                return vec![audio.to_vec()];
+                let whole = 0..audio.len();
+                return vec![whole];
             } else {
                 return Vec::new();
             }
         }
 
-        let step = chunk_samples - overlap_samples;
-        let mut chunks = Vec::new();
-        let mut pos = 0;
-
-        while pos < audio.len() {
-            let end = (pos + chunk_samples).min(audio.len());
-            let chunk = audio[pos..end].to_vec();
-            
-            if chunk.len() >= min_samples {
-                chunks.push(chunk);
-            }
-
-            pos += step;
-
-            // Avoid tiny last chunk
-            if audio.len() - pos < min_samples && !chunks.is_empty() {
-                break;
-            }
-        }
-
-        chunks
+        let overlap = ((self.overlap_secs * SAMPLE_RATE as f32) as usize).min(window / 2);
+        let stride = window - overlap;
+
+        // Fewest chunks of at most `window` samples that cover the audio
+        let count = (audio.len() - overlap).div_ceil(stride);
+        let size = (audio.len() + (count - 1) * overlap).div_ceil(count);
+        let step = size - overlap;
+
+        (0..count)
+            .map(|i| {
+                let start = i * step;
+                start..(start + size).min(audio.len())
+            })
+            .filter(|chunk| chunk.len() >= min_samples)
+            .collect()
     }
 
     /// Main processing pipeline: trim → normalize → chunk
-    pub fn process(&self, audio: &[f32]) -> Vec<Vec<f32>> {
+    pub fn process(&self, audio: &[f32]) -> Vec<AudioChunk> {
         // Step 1: Trim leading/trailing silence
-        let trimmed = self.trim_silence(audio);
-        
-        if trimmed.is_empty() {
+        let speech = self.trim_silence(audio);
+
+        if speech.is_empty() {
             return Vec::new();
         }
 
         // Step 2: Normalize
-        let normalized = self.normalize(&trimmed);
+        let normalized = self.normalize(&audio[speech.clone()]);
 
         // Step 3: Chunk with overlap
         self.chunk_with_overlap(&normalized)
+            .into_iter()
+            .map(|range| AudioChunk {
+                offset_secs: (speech.start + range.start) as f32 / SAMPLE_RATE as f32,
+                samples: normalized[range].to_vec(),
+            })
+            .collect()
     }
 }
 
@@ -183,11 +225,31 @@ mod tests {
         audio.extend(vec![0.5; 16000]);  // 1s of signal
         audio.extend(vec![0.0; 1600]);   // 100ms silence
         
-        let trimmed = processor.trim_silence(&audio);
+        let trimmed = &audio[processor.trim_silence(&audio)];
         assert!(trimmed.len() < audio.len());
         assert!(trimmed.len() >= 16000);
     }
 
+    #[test]
+    fn test_trim_silence_all_silent() {
+        let processor = AudioProcessor::default();
+        let silence = vec![0.0; SAMPLE_RATE];
+        assert!(processor.trim_silence(&silence).is_empty());
+        assert!(processor.process(&silence).is_empty());
+    }
+
+    #[test]
+    fn test_ends_with_pause() {
+        let processor = AudioProcessor::default();
+
+        let mut audio = vec![0.5; SAMPLE_RATE];
+        assert!(!processor.ends_with_pause(&audio, 0.5));
+
+        audio.extend(vec![0.001; SAMPLE_RATE / 2]);
+        assert!(processor.ends_with_pause(&audio, 0.5));
+        assert!(!processor.ends_with_pause(&audio, 0.8));
+    }
+
     #[test]
     fn test_normalize() {
         let processor = AudioProcessor::default();
@@ -215,4 +277,40 @@ mod tests {
         // Each chunk should be 2 seconds
         assert_eq!(chunks[0].len(), 2 * SAMPLE_RATE);
     }
+
+    #[test]
+    fn test_chunking_fits_whisper_window() {
+        let processor = AudioProcessor {
+            chunk_duration_secs: 45.0,
+            ..Default::default()
+        };
+
+        // 65 seconds: three balanced chunks instead of 30 + 30 + short tail
+        let audio = vec![0.5; 65 * SAMPLE_RATE];
+        let chunks = processor.chunk_with_overlap(&audio);
+        let window = (WHISPER_WINDOW_SECS * SAMPLE_RATE as f32) as usize;
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.len() <= window));
+        assert!(chunks.iter().all(|c| c.len() > window * 2 / 3));
+    }
+
+    #[test]
+    fn test_process_chunk_offsets() {
+        let processor = AudioProcessor {
+            chunk_duration_secs: 2.0,
+            overlap_secs: 0.5,
+            min_chunk_secs: 0.5,
+            ..Default::default()
+        };
+
+        // 1s of silence, then 5s of signal
+        let mut audio = vec![0.0; SAMPLE_RATE];
+        audio.extend(vec![0.5; 5 * SAMPLE_RATE]);
+        let chunks = processor.process(&audio);
+
+        assert_eq!(chunks.len(), 3);
+        assert!((chunks[0].offset_secs - 1.0).abs() < 0.02);
+        assert!((chunks[1].offset_secs - 2.5).abs() < 0.02);
+    }
 }
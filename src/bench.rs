@@ -0,0 +1,314 @@
+//! Latency benchmarks for chunk sizing and state reuse, and the glossary
+//! evaluation. The benchmarks run Whisper over synthetic speech-like audio so
+//! results are reproducible without recording fixtures.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::audio_processor::{AudioProcessor, SAMPLE_RATE, WHISPER_WINDOW_SECS};
+use crate::glossary::{contains_term, Glossary};
+use crate::whisper::{InferenceOptions, WhisperModel};
+
+/// Chunk sizes (seconds) compared by the benchmark
+const CHUNK_SIZES: &[f32] = &[10.0, 15.0, 20.0, 25.0, WHISPER_WINDOW_SECS];
+
+/// Length of the synthetic recording in seconds
+const BENCH_AUDIO_SECS: usize = 75;
+
+/// Generate deterministic speech-like audio: voiced "syllables" at ~4 Hz
+/// with a few harmonics, separated by short pauses and light noise.
+pub fn synthetic_speech(secs: usize) -> Vec<f32> {
+    let len = secs * SAMPLE_RATE;
+    let mut audio = Vec::with_capacity(len);
+    let mut seed: u32 = 0x1234_5678;
+
+    for n in 0..len {
+        let t = n as f32 / SAMPLE_RATE as f32;
+
+        // Pitch drifts slowly between ~110 and ~190 Hz
+        let pitch = 150.0 + 40.0 * (t * 0.7).sin();
+        let voiced: f32 = (1..=4)
+            .map(|h| (2.0 * std::f32::consts::PI * pitch * h as f32 * t).sin() / h as f32)
+            .sum();
+
+        // Syllable envelope, with a pause every 3 seconds
+        let syllable = (2.0 * std::f32::consts::PI * 4.0 * t).sin().max(0.0);
+        let envelope = if t % 3.0 > 2.5 { 0.0 } else { syllable };
+
+        // xorshift noise
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let noise = (seed as f32 / u32::MAX as f32 - 0.5) * 0.02;
+
+        audio.push(0.3 * envelope * voiced + noise);
+    }
+
+    audio
+}
+
+/// Chunks transcribed one by one in the state reuse benchmark
+const STATE_BENCH_CHUNKS: usize = 6;
+
+/// Measure transcription latency of each chunk size for every model
+pub fn run_chunk_benchmark(model_paths: &[PathBuf], inference: &InferenceOptions) -> anyhow::Result<()> {
+    let audio = synthetic_speech(BENCH_AUDIO_SECS);
+
+    println!("Chunk size benchmark ({}s synthetic audio)\n", BENCH_AUDIO_SECS);
+    println!(
+        "{:<28} {:>8} {:>7} {:>10} {:>10} {:>7}",
+        "model", "chunk s", "chunks", "total ms", "ms/chunk", "RTF"
+    );
+
+    for path in model_paths {
+        let model = WhisperModel::new(path, inference.clone())?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        for &secs in CHUNK_SIZES {
+            let processor = AudioProcessor {
+                chunk_duration_secs: secs,
+                ..Default::default()
+            };
+            let chunks = processor.process(&audio);
+
+            let started = Instant::now();
+            model.transcribe_chunks(&chunks)?;
+            let elapsed = started.elapsed().as_secs_f64();
+
+            println!(
+                "{:<28} {:>8.0} {:>7} {:>10.0} {:>10.0} {:>7.3}",
+                name,
+                secs,
+                chunks.len(),
+                elapsed * 1000.0,
+                elapsed * 1000.0 / chunks.len().max(1) as f64,
+                elapsed / BENCH_AUDIO_SECS as f64,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare per-chunk latency with a fresh state per chunk (before pooling)
+/// against states reused from the model's pool
+pub fn run_state_benchmark(model_paths: &[PathBuf], inference: &InferenceOptions) -> anyhow::Result<()> {
+    let processor = AudioProcessor {
+        chunk_duration_secs: 10.0,
+        ..Default::default()
+    };
+    let audio = synthetic_speech(10 * STATE_BENCH_CHUNKS);
+    let chunks = processor.process(&audio);
+
+    println!("State reuse benchmark ({} chunks of ~10s synthetic audio)\n", chunks.len());
+    println!("{:<28} {:>14} {:>14} {:>8}", "model", "fresh ms/chunk", "pooled ms/chunk", "speedup");
+
+    for path in model_paths {
+        let model = WhisperModel::new(path, inference.clone())?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        // Warm-up so both runs start with caches and pages loaded
+        model.transcribe_chunks(std::slice::from_ref(&chunks[0]))?;
+
+        let mut fresh = 0.0;
+        let mut pooled = 0.0;
+        for chunk in chunks.chunks(1) {
+            model.clear_state_pool();
+            let started = Instant::now();
+            model.transcribe_chunks(chunk)?;
+            fresh += started.elapsed().as_secs_f64();
+
+            let started = Instant::now();
+            model.transcribe_chunks(chunk)?;
+            pooled += started.elapsed().as_secs_f64();
+        }
+
+        let per_chunk = |total: f64| total * 1000.0 / chunks.len() as f64;
+        println!(
+            "{:<28} {:>14.0} {:>14.0} {:>7.2}x",
+            name,
+            per_chunk(fresh),
+            per_chunk(pooled),
+            fresh / pooled,
+        );
+    }
+
+    Ok(())
+}
+
+/// Logit bias used by the glossary evaluation when none is configured
+const EVAL_BOOST: f32 = 2.0;
+
+/// Read a 16 kHz WAV file as mono f32 samples
+fn read_wav(path: &Path) -> anyhow::Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let spec = reader.spec();
+    if spec.sample_rate != SAMPLE_RATE as u32 {
+        anyhow::bail!("{}: expected {} Hz, got {} Hz", path.display(), SAMPLE_RATE, spec.sample_rate);
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    // Downmix to mono
+    let channels = spec.channels.max(1) as usize;
+    Ok(samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect())
+}
+
+/// Glossary terms that occur in `reference`, and how many of them `hypothesis` has
+fn term_hits(glossary: &Glossary, reference: &str, hypothesis: &str) -> (usize, usize) {
+    let expected: Vec<&String> = glossary.terms().iter().filter(|t| contains_term(reference, t)).collect();
+    let found = expected.iter().filter(|t| contains_term(hypothesis, t)).count();
+    (expected.len(), found)
+}
+
+/// Reference transcripts in `fixtures`, sorted: every `<name>.txt` but the glossary
+fn references(fixtures: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut references: Vec<PathBuf> = fs::read_dir(fixtures)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", fixtures.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "txt") && !p.ends_with("glossary.txt"))
+        .collect();
+    references.sort();
+    Ok(references)
+}
+
+/// Measure how many glossary terms are recognized in the user's recordings
+/// without the glossary, with it in the prompt, and with boosting too.
+///
+/// `fixtures` holds `glossary.txt` and `<name>.txt` reference transcripts
+/// with `<name>.wav` (16 kHz) recordings of them; fixtures/glossary has a
+/// set to record. `model` is set up from the app's config, so only the
+/// glossary differs from what the app does.
+pub fn run_glossary_eval(mut model: WhisperModel, fixtures: &Path, boost: f32) -> anyhow::Result<()> {
+    let glossary = Glossary::load(&fixtures.join("glossary.txt"))?;
+    let boost = if boost > 0.0 { boost } else { EVAL_BOOST };
+
+    let (recordings, missing): (Vec<PathBuf>, Vec<PathBuf>) =
+        references(fixtures)?.into_iter().partition(|p| p.with_extension("wav").is_file());
+    for path in &missing {
+        println!("⚠️  No recording of {}, skipped", path.display());
+    }
+    if recordings.is_empty() {
+        anyhow::bail!("No <name>.wav recordings of the <name>.txt references in {}", fixtures.display());
+    }
+
+    let processor = AudioProcessor::default();
+    let configs: [(&str, bool, f32); 3] = [("none", false, 0.0), ("prompt", true, 0.0), ("boost", true, boost)];
+
+    println!(
+        "Glossary evaluation: {} terms, {} recordings, boost {:.1}, language {}, {:?}\n",
+        glossary.terms().len(),
+        recordings.len(),
+        boost,
+        model.language(),
+        model.decode_options().strategy
+    );
+    println!("{:<28} {:>6} {:>8} {:>8} {:>8}", "recording", "terms", "none", "prompt", "boost");
+
+    let mut totals = [0usize; 3];
+    let mut expected_total = 0;
+    for path in &recordings {
+        let reference = fs::read_to_string(path)?;
+        let chunks = processor.process(&read_wav(&path.with_extension("wav"))?);
+
+        let mut found = [0usize; 3];
+        let mut expected = 0;
+        for (i, (_, use_glossary, boost)) in configs.iter().enumerate() {
+            model.set_glossary(Some(&glossary).filter(|_| *use_glossary), *boost)?;
+            let hypothesis = model.transcribe_chunks(&chunks)?.text();
+            (expected, found[i]) = term_hits(&glossary, &reference, &hypothesis);
+        }
+
+        let name = path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        println!("{:<28} {:>6} {:>8} {:>8} {:>8}", name, expected, found[0], found[1], found[2]);
+        expected_total += expected;
+        for (total, n) in totals.iter_mut().zip(found) {
+            *total += n;
+        }
+    }
+
+    let recall = |n: usize| 100.0 * n as f32 / expected_total.max(1) as f32;
+    println!(
+        "{:<28} {:>6} {:>7.0}% {:>7.0}% {:>7.0}%",
+        "recall",
+        expected_total,
+        recall(totals[0]),
+        recall(totals[1]),
+        recall(totals[2])
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_term_hits() {
+        let glossary = Glossary::parse("Кузнецов\nВойсАгент\nWhisper\n");
+        let reference = "Кузнецов запустил ВойсАгент.";
+
+        assert_eq!(term_hits(&glossary, reference, "Кузнецов запустил Войс агент."), (2, 1));
+        assert_eq!(term_hits(&glossary, reference, "кузнецов запустил войсагент"), (2, 2));
+        assert_eq!(term_hits(&glossary, "Ничего.", "Whisper"), (0, 0));
+    }
+
+    #[test]
+    fn test_fixtures_use_glossary_terms() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/glossary");
+        let glossary = Glossary::load(&fixtures.join("glossary.txt")).unwrap();
+        let references = references(&fixtures).unwrap();
+        assert!(!references.is_empty());
+
+        // Every reference has terms to recognize, and every term is used
+        let mut used = Vec::new();
+        for path in &references {
+            let reference = fs::read_to_string(path).unwrap();
+            assert!(term_hits(&glossary, &reference, "").0 >= 2, "{}", path.display());
+            used.extend(glossary.terms().iter().filter(|t| contains_term(&reference, t)));
+        }
+        for term in glossary.terms() {
+            assert!(used.contains(&term), "{} is in no reference", term);
+        }
+    }
+
+    #[test]
+    fn test_read_wav() {
+        let path = std::env::temp_dir().join(format!("voice-agent-test-{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: SAMPLE_RATE as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in [16384_i16, 0, -16384, -16384] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = read_wav(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(samples, vec![0.25, -0.5]);
+    }
+}
@@ -1,5 +1,5 @@
-/// Voice calibration system for personalized Whisper transcription.
-/// Creates a voice profile from reference phrases to improve accuracy.
+//! Voice calibration system for personalized Whisper transcription.
+//! Creates a voice profile from reference phrases to improve accuracy.
 
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -8,7 +8,9 @@ use std::path::PathBuf;
 
 use crate::audio::AudioRecorder;
 use crate::audio_processor::AudioProcessor;
-use crate::whisper::WhisperModel;
+use crate::config;
+use crate::prompt;
+use crate::transcriber::Transcriber;
 
 /// Calibration phrases in Russian - designed to cover common sounds and vocabulary
 pub const CALIBRATION_PHRASES: &[&str] = &[
@@ -23,21 +25,19 @@ pub const CALIBRATION_PHRASES: &[&str] = &[
 /// Voice profile containing calibration data
 #[derive(Serialize, Deserialize, Default)]
 pub struct VoiceProfile {
-    /// Calibration prompt text (last 300 chars of transcriptions)
+    /// Calibration prompt text (last 300 characters of transcriptions)
     pub prompt: String,
     /// ISO timestamp when profile was created
     pub created_at: String,
+    /// Preferred language, used when none is given on the command line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
 impl VoiceProfile {
-    /// Get the config directory path
-    fn config_dir() -> Option<PathBuf> {
-        dirs::config_dir().map(|p| p.join("voice-agent"))
-    }
-
     /// Get the profile file path
     fn profile_path() -> Option<PathBuf> {
-        Self::config_dir().map(|p| p.join("profile.json"))
+        config::config_dir().map(|p| p.join("profile.json"))
     }
 
     /// Load profile from disk, returns None if not found
@@ -49,7 +49,7 @@ impl VoiceProfile {
 
     /// Save profile to disk
     pub fn save(&self) -> anyhow::Result<()> {
-        let dir = Self::config_dir()
+        let dir = config::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Cannot find config directory"))?;
         
         fs::create_dir_all(&dir)?;
@@ -69,10 +69,59 @@ impl VoiceProfile {
     }
 }
 
-/// Run the calibration process interactively
-pub fn run_calibration(
-    whisper: &WhisperModel,
+/// Outcome of transcribing one calibration phrase
+#[derive(Debug, PartialEq)]
+enum PhraseResult {
+    /// Transcribed text, added to the profile
+    Recorded(String),
+    /// Only silence was recorded
+    NoSpeech,
+    /// Too little text to be useful
+    TooShort,
+    /// Transcription error
+    Failed(String),
+}
+
+/// Transcribe a recorded calibration phrase
+fn transcribe_phrase<T: Transcriber + ?Sized>(
+    transcriber: &T,
+    processor: &AudioProcessor,
+    audio: &[f32],
+) -> PhraseResult {
+    let chunks = processor.process(audio);
+    if chunks.is_empty() {
+        return PhraseResult::NoSpeech;
+    }
+
+    match transcriber.transcribe_chunks(&chunks) {
+        Ok(transcript) => {
+            let text = transcript.text();
+            let trimmed = text.trim();
+            if trimmed.len() > 5 {
+                PhraseResult::Recorded(trimmed.to_string())
+            } else {
+                PhraseResult::TooShort
+            }
+        }
+        Err(e) => PhraseResult::Failed(e.to_string()),
+    }
+}
+
+/// Characters of transcribed text kept in the profile prompt
+const PROMPT_CHARS: usize = 300;
+
+/// Build the profile prompt from the collected transcriptions: the last
+/// whole words within `PROMPT_CHARS` characters
+fn build_prompt(collected_text: &str) -> String {
+    prompt::tail_within(collected_text, PROMPT_CHARS, |t| t.chars().count()).to_string()
+}
+
+/// Run the calibration process interactively.
+/// `language` is stored in the profile as its preferred language.
+pub fn run_calibration<T: Transcriber + ?Sized>(
+    whisper: &T,
     recorder: &AudioRecorder,
+    language: Option<&str>,
 ) -> anyhow::Result<VoiceProfile> {
     use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
     use crossterm::event::{self, Event, KeyCode};
@@ -138,43 +187,25 @@ pub fn run_calibration(
         }
 
         // Process and transcribe
-        let chunks = processor.process(&audio);
-        if chunks.is_empty() {
-            print!("   ⚠️  Речь не обнаружена\r\n");
-            io::stdout().flush()?;
-            continue;
-        }
-
-        match whisper.transcribe_chunks(&chunks) {
-            Ok(text) => {
-                let trimmed = text.trim();
-                if trimmed.len() > 5 {
-                    print!("   ✅ Записано: \"{}\"\r\n", trimmed);
-                    collected_text.push(' ');
-                    collected_text.push_str(trimmed);
-                } else {
-                    print!("   ⚠️  Слишком коротко, попробуйте ещё раз\r\n");
-                }
-            }
-            Err(e) => {
-                print!("   ❌ Ошибка: {}\r\n", e);
+        match transcribe_phrase(whisper, &processor, &audio) {
+            PhraseResult::Recorded(text) => {
+                print!("   ✅ Записано: \"{}\"\r\n", text);
+                collected_text.push(' ');
+                collected_text.push_str(&text);
             }
+            PhraseResult::NoSpeech => print!("   ⚠️  Речь не обнаружена\r\n"),
+            PhraseResult::TooShort => print!("   ⚠️  Слишком коротко, попробуйте ещё раз\r\n"),
+            PhraseResult::Failed(e) => print!("   ❌ Ошибка: {}\r\n", e),
         }
         io::stdout().flush()?;
     }
 
     disable_raw_mode()?;
 
-    // Build profile (last 300 chars)
-    let prompt = if collected_text.len() > 300 {
-        collected_text[collected_text.len() - 300..].to_string()
-    } else {
-        collected_text.trim().to_string()
-    };
-
     let profile = VoiceProfile {
-        prompt,
+        prompt: build_prompt(&collected_text),
         created_at: chrono_lite_now(),
+        language: language.map(str::to_string),
     };
 
     // Save profile
@@ -194,3 +225,50 @@ fn chrono_lite_now() -> String {
         .unwrap_or_default();
     format!("{}", duration.as_secs())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::SAMPLE_RATE;
+    use crate::transcriber::fake::ScriptedTranscriber;
+
+    fn tone(secs: usize) -> Vec<f32> {
+        (0..secs * SAMPLE_RATE).map(|i| (i as f32 * 0.05).sin() * 0.5).collect()
+    }
+
+    #[test]
+    fn test_transcribe_phrase() {
+        let processor = AudioProcessor::default();
+        let transcriber = ScriptedTranscriber::new(&[" Раз два три четыре пять. ", " Да."])
+            .then_fail("model crashed");
+
+        assert_eq!(
+            transcribe_phrase(&transcriber, &processor, &tone(2)),
+            PhraseResult::Recorded("Раз два три четыре пять.".to_string())
+        );
+        assert_eq!(transcribe_phrase(&transcriber, &processor, &tone(2)), PhraseResult::TooShort);
+        assert_eq!(
+            transcribe_phrase(&transcriber, &processor, &tone(2)),
+            PhraseResult::Failed("model crashed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_silent_phrase_is_not_transcribed() {
+        let transcriber = ScriptedTranscriber::new(&[" Продолжение следует..."]);
+        let result = transcribe_phrase(&transcriber, &AudioProcessor::default(), &vec![0.0; SAMPLE_RATE]);
+
+        assert_eq!(result, PhraseResult::NoSpeech);
+        assert_eq!(transcriber.remaining(), 1);
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        assert_eq!(build_prompt(" Раз два."), "Раз два.");
+        // Cyrillic is two bytes per character; this used to panic mid-character
+        let long = "ж".repeat(7) + &" слово".repeat(60);
+        let prompt = build_prompt(&long);
+        assert!(prompt.chars().count() <= PROMPT_CHARS);
+        assert!(prompt.starts_with("слово"));
+    }
+}
@@ -0,0 +1,93 @@
+//! Cancelling a running transcription.
+//! whisper.cpp polls an abort callback while encoding and decoding; it
+//! checks a `Cancel`, which trips when its `CancelFlag` is raised (by a key
+//! press) or when the transcription runs past its time limit.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Why a transcription stopped early
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CancelReason {
+    /// Cancelled by the user
+    Requested,
+    /// Ran longer than the configured timeout
+    Timeout,
+}
+
+/// Flag shared with the UI to cancel the running transcription
+#[derive(Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    /// Ask the running transcription to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Lower the flag as the next job starts, so a press meant for the
+    /// previous one does not cancel it
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    fn is_raised(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Cancellation state of one transcription
+#[derive(Clone)]
+pub struct Cancel {
+    flag: CancelFlag,
+    deadline: Option<Instant>,
+}
+
+impl Cancel {
+    /// Start a transcription: start the timeout. The flag is left as it
+    /// is: raised since its job was taken up, it cancels this transcription.
+    pub fn start(flag: &CancelFlag, timeout: Option<Duration>) -> Self {
+        Self {
+            flag: flag.clone(),
+            deadline: timeout.map(|t| Instant::now() + t),
+        }
+    }
+
+    /// Reason to stop, if any; a user request wins over the timeout
+    pub fn reason(&self) -> Option<CancelReason> {
+        if self.flag.is_raised() {
+            Some(CancelReason::Requested)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(CancelReason::Timeout)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_reasons() {
+        let flag = CancelFlag::default();
+        let cancel = Cancel::start(&flag, None);
+        assert_eq!(cancel.reason(), None);
+        flag.cancel();
+        assert_eq!(cancel.reason(), Some(CancelReason::Requested));
+
+        // A press before the transcription started still counts
+        assert!(Cancel::start(&flag, None).is_cancelled());
+
+        flag.reset();
+        let cancel = Cancel::start(&flag, Some(Duration::ZERO));
+        assert_eq!(cancel.reason(), Some(CancelReason::Timeout));
+        assert_eq!(Cancel::start(&flag, Some(Duration::from_secs(60))).reason(), None);
+    }
+}
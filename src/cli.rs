@@ -0,0 +1,182 @@
+//! Command-line argument parsing.
+
+/// Arguments that are commands, never model paths after `--bench`
+const SUBCOMMANDS: &[&str] = &["models"];
+
+/// Parsed command-line arguments
+#[derive(Default)]
+pub struct CliArgs {
+    /// Force recalibration (`--calibrate`, `-c`)
+    pub calibrate: bool,
+    /// Model file override (`--model <path>`, `-m <path>`)
+    pub model: Option<String>,
+    /// Language for this session (`--language <code>`, `-l <code>`)
+    pub language: Option<String>,
+    /// Translate speech to English (`--translate`, `-t`)
+    pub translate: bool,
+    /// Show the original transcript next to the translation (`--show-original`)
+    pub show_original: bool,
+    /// Show partial transcripts while recording (`--stream`, `-s`)
+    pub stream: bool,
+    /// Mark speaker turns with a tinydiarize model (`--diarize`, `-d`)
+    pub diarize: bool,
+    /// Decode chunks and segments in English when detected (`--code-switch`, `-x`)
+    pub code_switch: bool,
+    /// Save each transcript as .txt and .srt here (`--export <dir>`)
+    pub export: Option<String>,
+    /// Print log messages, including whisper.cpp's (`--verbose`, `-v`)
+    pub verbose: bool,
+    /// Inference thread count (`--threads <n>`)
+    pub threads: Option<usize>,
+    /// Chunks transcribed concurrently (`--parallel <n>`)
+    pub parallel: Option<usize>,
+    /// Glossary file override (`--glossary <path>`, `-g <path>`)
+    pub glossary: Option<String>,
+    /// Evaluate glossary recognition on these fixtures (`--eval-glossary <dir>`)
+    pub eval_glossary: Option<String>,
+    /// List installed models with their metadata (`models`)
+    pub list_models: bool,
+    /// Run the chunk benchmark over these models (`--bench [model ...]`)
+    pub bench: Option<Vec<String>>,
+    /// Run the state reuse benchmark over these models (`--bench-states [model ...]`)
+    pub bench_states: Option<Vec<String>>,
+}
+
+impl CliArgs {
+    /// Parse arguments, skipping the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> anyhow::Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().skip(1).peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "models" => parsed.list_models = true,
+                "--calibrate" | "-c" => parsed.calibrate = true,
+                "--translate" | "-t" => parsed.translate = true,
+                "--show-original" => parsed.show_original = true,
+                "--stream" | "-s" => parsed.stream = true,
+                "--diarize" | "-d" => parsed.diarize = true,
+                "--code-switch" | "-x" => parsed.code_switch = true,
+                "--verbose" | "-v" => parsed.verbose = true,
+                "--model" | "-m" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("{} requires a path", arg))?;
+                    parsed.model = Some(path);
+                }
+                "--glossary" | "-g" | "--eval-glossary" | "--export" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("{} requires a path", arg))?;
+                    match arg.as_str() {
+                        "--eval-glossary" => parsed.eval_glossary = Some(path),
+                        "--export" => parsed.export = Some(path),
+                        _ => parsed.glossary = Some(path),
+                    }
+                }
+                "--language" | "-l" => {
+                    let language = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("{} requires a language code", arg))?;
+                    parsed.language = Some(language);
+                }
+                "--threads" | "--parallel" => {
+                    let n = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| anyhow::anyhow!("{} requires a number", arg))?;
+                    if arg == "--threads" {
+                        parsed.threads = Some(n);
+                    } else {
+                        parsed.parallel = Some(n);
+                    }
+                }
+                "--bench" | "--bench-states" => {
+                    let mut models = Vec::new();
+                    while let Some(model) = args.next_if(|a| !a.starts_with('-') && !SUBCOMMANDS.contains(&a.as_str())) {
+                        models.push(model);
+                    }
+                    if arg == "--bench" {
+                        parsed.bench = Some(models);
+                    } else {
+                        parsed.bench_states = Some(models);
+                    }
+                }
+                other => anyhow::bail!("Unknown argument: {}", other),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<CliArgs> {
+        let args = std::iter::once("voice-agent").chain(args.iter().copied());
+        CliArgs::parse(args.map(String::from))
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let args = parse(&["-c", "--model", "m.bin", "-l", "auto", "-s"]).unwrap();
+        assert!(args.calibrate);
+        assert!(args.stream);
+        assert_eq!(args.model.as_deref(), Some("m.bin"));
+        assert_eq!(args.language.as_deref(), Some("auto"));
+
+        let args = parse(&["-g", "terms.txt", "--eval-glossary", "fixtures"]).unwrap();
+        assert_eq!(args.glossary.as_deref(), Some("terms.txt"));
+        assert_eq!(args.eval_glossary.as_deref(), Some("fixtures"));
+
+        let args = parse(&["-d", "--export", "notes", "-v", "-x"]).unwrap();
+        assert!(args.diarize);
+        assert!(args.code_switch);
+        assert!(args.verbose);
+        assert_eq!(args.export.as_deref(), Some("notes"));
+        assert!(args.bench.is_none());
+        assert!(!args.list_models);
+        assert!(parse(&["models"]).unwrap().list_models);
+    }
+
+    #[test]
+    fn test_parse_translate() {
+        let args = parse(&["--translate", "--show-original"]).unwrap();
+        assert!(args.translate);
+        assert!(args.show_original);
+
+        let args = parse(&["-t"]).unwrap();
+        assert!(args.translate);
+        assert!(!args.show_original);
+        assert!(!parse(&[]).unwrap().translate);
+    }
+
+    #[test]
+    fn test_parse_bench_models() {
+        let args = parse(&["--bench", "a.bin", "b.bin", "-c"]).unwrap();
+        assert_eq!(args.bench, Some(vec!["a.bin".to_string(), "b.bin".to_string()]));
+        assert!(args.calibrate);
+        assert!(!args.stream);
+
+        let args = parse(&["--bench", "a.bin", "models"]).unwrap();
+        assert_eq!(args.bench, Some(vec!["a.bin".to_string()]));
+        assert!(args.list_models);
+
+        let args = parse(&["--bench-states"]).unwrap();
+        assert_eq!(args.bench_states, Some(Vec::new()));
+        assert!(args.bench.is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--model"]).is_err());
+        assert!(parse(&["--language"]).is_err());
+        assert!(parse(&["--glossary"]).is_err());
+        assert!(parse(&["--export"]).is_err());
+        assert!(parse(&["--threads", "many"]).is_err());
+        assert!(parse(&["--parallel"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+}
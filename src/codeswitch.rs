@@ -0,0 +1,283 @@
+//! Mixed-language speech: Russian with English technical terms.
+//! In code-switching mode each chunk's language is detected, and English
+//! chunks or segments are decoded as English instead of being transliterated
+//! into Cyrillic. A user dictionary maps transliterations that remain back
+//! to their Latin spelling; it lives at ~/.config/voice-agent/transliterations.txt,
+//! one `кубернетес* = Kubernetes` entry per line, `#` starts a comment.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::whisper::{Segment, Word};
+
+/// Code-switching settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodeSwitchOptions {
+    /// Detect each chunk's language and decode chunks in the second
+    /// language with it (default: false)
+    pub enabled: bool,
+    /// Language mixed into the session language (default: "en")
+    pub second_language: String,
+    /// Also re-decode single segments detected as the second language,
+    /// at the cost of a detection per segment (default: false)
+    pub redecode_segments: bool,
+    /// Detection probability the second language needs to switch (default: 0.5)
+    pub min_probability: f32,
+    /// Transliteration dictionary (default: transliterations.txt in the
+    /// config dir, if present); applied whether or not `enabled` is set
+    pub dictionary: Option<PathBuf>,
+}
+
+impl Default for CodeSwitchOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            second_language: "en".to_string(),
+            redecode_segments: false,
+            min_probability: 0.5,
+            dictionary: None,
+        }
+    }
+}
+
+impl CodeSwitchOptions {
+    /// Whether audio with these detection probabilities of the session and
+    /// the second language should be decoded as the second language
+    pub fn switches(&self, session_prob: f32, second_prob: f32) -> bool {
+        second_prob >= self.min_probability && second_prob > session_prob
+    }
+
+    /// Load the configured dictionary, `None` when there is none
+    pub fn load_dictionary(&self) -> anyhow::Result<Option<Transliterations>> {
+        match &self.dictionary {
+            Some(path) => Transliterations::load(path).map(Some),
+            None => match config::config_dir().map(|d| d.join("transliterations.txt")) {
+                Some(path) if path.is_file() => Transliterations::load(&path).map(Some),
+                _ => Ok(None),
+            },
+        }
+    }
+}
+
+/// One dictionary entry
+#[derive(Clone, Debug)]
+struct Entry {
+    /// Lowercase words to match; a trailing `*` matches any ending
+    words: Vec<String>,
+    /// Latin spelling
+    replacement: String,
+}
+
+/// Lowercase word without surrounding punctuation
+fn core(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Punctuation before and after the word itself
+fn punctuation(word: &str) -> (&str, &str) {
+    let not_word = |c: char| !c.is_alphanumeric();
+    let start = word.len() - word.trim_start_matches(not_word).len();
+    let end = word.trim_end_matches(not_word).len().max(start);
+    (&word[..start], &word[end..])
+}
+
+impl Entry {
+    fn matches(&self, words: &[&str]) -> bool {
+        words.len() >= self.words.len()
+            && self.words.iter().zip(words).all(|(pattern, word)| {
+                let word = core(word);
+                match pattern.strip_suffix('*') {
+                    Some(stem) => word.starts_with(stem),
+                    None => word == *pattern,
+                }
+            })
+    }
+}
+
+/// Transliterated terms mapped back to their Latin spelling
+#[derive(Clone, Debug, Default)]
+pub struct Transliterations {
+    /// Longest entries first, so "пул реквест" wins over "пул"
+    entries: Vec<Entry>,
+}
+
+impl Transliterations {
+    /// Parse dictionary text: `transliteration = Latin` per line, blank
+    /// lines and `#` comments skipped
+    pub fn parse(text: &str) -> Self {
+        let mut entries: Vec<Entry> = text
+            .lines()
+            .filter_map(|line| {
+                let (from, to) = line.split('#').next()?.split_once('=')?;
+                let words: Vec<String> = from.split_whitespace().map(str::to_lowercase).collect();
+                let replacement = to.trim();
+                (!words.is_empty() && !replacement.is_empty())
+                    .then(|| Entry { words, replacement: replacement.to_string() })
+            })
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.words.len()));
+        Self { entries }
+    }
+
+    /// Read a dictionary file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read dictionary {}: {}", path.display(), e))?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Matches in `words` as (first word, word count, replacement), left to right
+    fn find(&self, words: &[&str]) -> Vec<(usize, usize, &str)> {
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            match self.entries.iter().find(|e| e.matches(&words[i..])) {
+                Some(entry) => {
+                    found.push((i, entry.words.len(), entry.replacement.as_str()));
+                    i += entry.words.len();
+                }
+                None => i += 1,
+            }
+        }
+        found
+    }
+
+    /// Replace the matched words, keeping punctuation around the match
+    fn replace(words: &[&str], first: usize, count: usize, replacement: &str) -> String {
+        let (lead, _) = punctuation(words[first]);
+        let (_, trail) = punctuation(words[first + count - 1]);
+        format!("{}{}{}", lead, replacement, trail)
+    }
+
+    /// Restore the Latin spelling in text, whole words only
+    pub fn apply_text(&self, text: &str) -> String {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let found = self.find(&words);
+        if found.is_empty() {
+            return text.to_string();
+        }
+
+        let mut result: Vec<String> = Vec::new();
+        let mut i = 0;
+        for (first, count, replacement) in found {
+            result.extend(words[i..first].iter().map(|w| w.to_string()));
+            result.push(Self::replace(&words, first, count, replacement));
+            i = first + count;
+        }
+        result.extend(words[i..].iter().map(|w| w.to_string()));
+
+        let joined = result.join(" ");
+        if text.starts_with(' ') {
+            format!(" {}", joined)
+        } else {
+            joined
+        }
+    }
+
+    /// Respell the words of each match as one word spanning their time
+    fn apply_words(&self, words: Vec<Word>) -> Vec<Word> {
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        let found: Vec<(usize, usize, String)> = self
+            .find(&texts)
+            .into_iter()
+            .map(|(first, count, replacement)| (first, count, Self::replace(&texts, first, count, replacement)))
+            .collect();
+        if found.is_empty() {
+            return words;
+        }
+
+        let mut result = Vec::with_capacity(words.len());
+        let mut words = words.into_iter();
+        let mut next = 0;
+        for (first, count, text) in found {
+            result.extend(words.by_ref().take(first - next));
+            let matched: Vec<Word> = words.by_ref().take(count).collect();
+            let last = &matched[matched.len() - 1];
+            result.push(Word {
+                start: matched[0].start,
+                end: last.end,
+                text,
+                probability: matched.iter().map(|w| w.probability).fold(1.0, f32::min),
+            });
+            next = first + count;
+        }
+        result.extend(words);
+        result
+    }
+
+    /// Restore the Latin spelling in segments and their words
+    pub fn apply(&self, segments: Vec<Segment>) -> Vec<Segment> {
+        if self.entries.is_empty() {
+            return segments;
+        }
+        segments
+            .into_iter()
+            .map(|mut segment| {
+                segment.text = self.apply_text(&segment.text);
+                segment.words = self.apply_words(std::mem::take(&mut segment.words));
+                segment
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICTIONARY: &str = "# devops\nкубернетес* = Kubernetes\nпул реквест* = pull request\nпул = pool\nбад строка\n";
+
+    fn word(text: &str, start: f32, probability: f32) -> Word {
+        Word { start, end: start + 0.5, text: text.to_string(), probability }
+    }
+
+    #[test]
+    fn test_parse_dictionary() {
+        let dictionary = Transliterations::parse(DICTIONARY);
+        assert_eq!(dictionary.len(), 3);
+        assert_eq!(dictionary.entries[0].words, ["пул", "реквест*"]);
+        assert!(Transliterations::parse("# пусто\n= x\ny =\n").is_empty());
+    }
+
+    #[test]
+    fn test_apply_text() {
+        let dictionary = Transliterations::parse(DICTIONARY);
+        assert_eq!(
+            dictionary.apply_text(" Задеплой в Кубернетес, потом открой пул реквесты."),
+            " Задеплой в Kubernetes, потом открой pull request."
+        );
+        assert_eq!(dictionary.apply_text(" Пул потоков (кубернетеса)"), " pool потоков (Kubernetes)");
+        assert_eq!(dictionary.apply_text(" Пулемет"), " Пулемет");
+    }
+
+    #[test]
+    fn test_apply_words_merges_matches() {
+        let dictionary = Transliterations::parse(DICTIONARY);
+        let words = vec![word("открой", 0.0, 0.9), word("пул", 1.0, 0.8), word("реквест.", 1.5, 0.4)];
+        let words = dictionary.apply_words(words);
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[1].text, "pull request.");
+        assert_eq!((words[1].start, words[1].end), (1.0, 2.0));
+        assert_eq!(words[1].probability, 0.4);
+    }
+
+    #[test]
+    fn test_switches_language() {
+        let options = CodeSwitchOptions::default();
+        assert!(options.switches(0.3, 0.6));
+        assert!(!options.switches(0.6, 0.3));
+        assert!(!options.switches(0.2, 0.4), "below min_probability");
+    }
+}
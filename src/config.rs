@@ -0,0 +1,211 @@
+//! Application configuration and model discovery.
+//! Settings live in ~/.config/voice-agent/config.json next to the voice profile.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::codeswitch::CodeSwitchOptions;
+use crate::glossary::GlossaryOptions;
+use crate::prompt::PromptOptions;
+use crate::streaming::StreamOptions;
+use crate::suppress::SuppressOptions;
+use crate::whisper::{DecodeOptions, FilterOptions, InferenceOptions};
+
+/// Environment variable overriding the model path
+pub const MODEL_ENV_VAR: &str = "VOICE_AGENT_MODEL";
+
+/// Model file looked up when no path is configured
+pub const DEFAULT_MODEL_FILE: &str = "ggml-base.bin";
+
+/// Get the config directory path (~/.config/voice-agent)
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("voice-agent"))
+}
+
+/// Get the data directory path (~/.local/share/voice-agent)
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("voice-agent"))
+}
+
+/// User configuration, every field optional
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Path to the Whisper model file
+    pub model_path: Option<PathBuf>,
+    /// Check models against the SHA256SUMS manifest next to them before loading
+    pub verify_models: bool,
+    /// Transcription language ("ru", "en", "uk", ... or "auto")
+    pub language: Option<String>,
+    /// Start in translate-to-English mode
+    pub translate: bool,
+    /// In translate mode, also show the original transcript
+    pub show_original: bool,
+    /// Mark speaker turns (needs a tinydiarize model)
+    pub diarize: bool,
+    /// Directory where each transcript is saved as .txt and .srt
+    pub export_dir: Option<PathBuf>,
+    /// Decoding settings (strategy, temperature, thresholds)
+    pub decode: DecodeOptions,
+    /// Hallucination filter (thresholds, phrase blocklist)
+    pub filter: FilterOptions,
+    /// Non-speech token suppression and annotation stripping
+    pub suppress: SuppressOptions,
+    /// Mixed-language decoding and transliteration dictionary
+    pub code_switch: CodeSwitchOptions,
+    /// Initial prompt token budget
+    pub prompt: PromptOptions,
+    /// Glossary file and term boosting
+    pub glossary: GlossaryOptions,
+    /// Threads, encoder context and flash attention
+    pub inference: InferenceOptions,
+    /// Live partial transcripts while recording
+    pub stream: StreamOptions,
+}
+
+impl Config {
+    /// Get the config file path
+    fn config_path() -> Option<PathBuf> {
+        config_dir().map(|p| p.join("config.json"))
+    }
+
+    /// Load config from disk, returns defaults if the file does not exist
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::config_path().filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let data = fs::read_to_string(&path)?;
+        serde_json::from_str(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+}
+
+/// Find the Whisper model file.
+///
+/// An explicit path (CLI, then environment, then config file) is used as is.
+/// Otherwise the default model is searched in ./models and in the data dir.
+pub fn resolve_model_path(cli_path: Option<&str>, config: &Config) -> anyhow::Result<PathBuf> {
+    let explicit = cli_path
+        .map(|p| (PathBuf::from(p), "--model".to_string()))
+        .or_else(|| {
+            env::var(MODEL_ENV_VAR)
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(|p| (PathBuf::from(p), format!("${}", MODEL_ENV_VAR)))
+        })
+        .or_else(|| {
+            config
+                .model_path
+                .clone()
+                .map(|p| (p, "config.json".to_string()))
+        });
+
+    let candidates = match explicit {
+        Some(candidate) => vec![candidate],
+        None => default_model_locations(),
+    };
+
+    find_model(&candidates)
+}
+
+/// Default places to look for the model, in search order
+fn default_model_locations() -> Vec<(PathBuf, String)> {
+    let mut locations = vec![(
+        Path::new("models").join(DEFAULT_MODEL_FILE),
+        "current directory".to_string(),
+    )];
+    if let Some(dir) = data_dir() {
+        locations.push((dir.join("models").join(DEFAULT_MODEL_FILE), "data dir".to_string()));
+    }
+    locations
+}
+
+/// Model files installed in ./models and the data dir, sorted by name
+pub fn installed_models() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("models")];
+    dirs.extend(data_dir().map(|d| d.join("models")));
+
+    let mut models: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "bin"))
+        .collect();
+    models.sort_by_key(|p| p.file_name().map(|n| n.to_os_string()));
+    models
+}
+
+/// The model after `current` in `models`, wrapping around; the first one
+/// when `current` is not in the list
+pub fn next_model<'a>(models: &'a [PathBuf], current: &Path) -> Option<&'a PathBuf> {
+    let position = models.iter().position(|m| m == current);
+    let next = position.map_or(0, |i| (i + 1) % models.len());
+    models.get(next)
+}
+
+/// Return the first existing candidate, or an error listing all of them
+fn find_model(candidates: &[(PathBuf, String)]) -> anyhow::Result<PathBuf> {
+    if let Some((path, _)) = candidates.iter().find(|(p, _)| p.is_file()) {
+        return Ok(path.clone());
+    }
+
+    let searched: String = candidates
+        .iter()
+        .map(|(path, source)| format!("\n  - {} ({})", path.display(), source))
+        .collect();
+    anyhow::bail!(
+        "Whisper model not found. Searched:{}\n\
+         Pass --model <path>, set ${}, or set \"model_path\" in config.json",
+        searched,
+        MODEL_ENV_VAR
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::DecodeStrategy;
+
+    #[test]
+    fn test_missing_model_lists_locations() {
+        let candidates = vec![
+            (PathBuf::from("/nonexistent/a.bin"), "--model".to_string()),
+            (PathBuf::from("/nonexistent/b.bin"), "data dir".to_string()),
+        ];
+        let err = find_model(&candidates).unwrap_err().to_string();
+
+        assert!(err.contains("/nonexistent/a.bin (--model)"));
+        assert!(err.contains("/nonexistent/b.bin (data dir)"));
+    }
+
+    #[test]
+    fn test_next_model_wraps_around() {
+        let models = vec![PathBuf::from("models/ggml-base.bin"), PathBuf::from("models/ggml-small.bin")];
+        assert_eq!(next_model(&models, Path::new("models/ggml-base.bin")), Some(&models[1]));
+        assert_eq!(next_model(&models, Path::new("models/ggml-small.bin")), Some(&models[0]));
+        assert_eq!(next_model(&models, Path::new("other.bin")), Some(&models[0]));
+        assert_eq!(next_model(&[], Path::new("other.bin")), None);
+    }
+
+    #[test]
+    fn test_config_fields_are_optional() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert!(config.model_path.is_none());
+        assert!(config.language.is_none());
+    }
+
+    #[test]
+    fn test_decode_options_from_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "decode": { "strategy": "beam_search", "beam_size": 8 } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.decode.strategy, DecodeStrategy::BeamSearch);
+        assert_eq!(config.decode.beam_size, 8);
+        assert_eq!(config.decode.temperature_inc, 0.2);
+        assert!(config.decode.validate().is_ok());
+    }
+}
@@ -0,0 +1,132 @@
+//! Saving transcripts as plain text and SRT subtitles.
+//! Speaker turns start a new paragraph in text and are marked with
+//! `[SPEAKER_TURN]` in SRT, as whisper.cpp does.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::whisper::Transcript;
+
+/// Marker appended to the last cue before a speaker change
+const SPEAKER_TURN: &str = "[SPEAKER_TURN]";
+
+/// Plain text, one paragraph per speaker turn, each starting with a dash
+/// when there is more than one
+pub fn to_text(transcript: &Transcript) -> String {
+    let turns = transcript.turns();
+    let paragraphs: Vec<String> = turns
+        .iter()
+        .map(|turn| turn.iter().map(|s| s.text.as_str()).collect::<String>().trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    if paragraphs.len() > 1 {
+        paragraphs.iter().map(|p| format!("- {}\n", p)).collect()
+    } else {
+        paragraphs.iter().map(|p| format!("{}\n", p)).collect()
+    }
+}
+
+/// Format seconds as an SRT timestamp, HH:MM:SS,mmm
+fn srt_time(secs: f32) -> String {
+    let ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02},{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+/// SRT subtitles, one cue per segment
+pub fn to_srt(transcript: &Transcript) -> String {
+    transcript
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let turn = if s.speaker_turn_next { format!(" {}", SPEAKER_TURN) } else { String::new() };
+            format!("{}\n{} --> {}\n{}{}\n\n", i + 1, srt_time(s.start), srt_time(s.end), s.text.trim(), turn)
+        })
+        .collect()
+}
+
+/// Write the transcript to `dir` as `<unix time>.txt` and `.srt`,
+/// returning the text file's path. Recordings saved within the same second
+/// get `-1`, `-2`, ... suffixes instead of overwriting each other.
+pub fn save(dir: &Path, transcript: &Transcript) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    for n in 0.. {
+        let name = if n == 0 { stamp.to_string() } else { format!("{}-{}", stamp, n) };
+        let text_path = dir.join(format!("{}.txt", name));
+        // Claim the name atomically, so a concurrent save cannot take it too
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&text_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => anyhow::bail!("Failed to create {}: {}", text_path.display(), e),
+        };
+        file.write_all(to_text(transcript).as_bytes())?;
+        fs::write(text_path.with_extension("srt"), to_srt(transcript))?;
+        return Ok(text_path);
+    }
+    unreachable!("ran out of file name suffixes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::Segment;
+
+    fn segment(start: f32, end: f32, text: &str, speaker_turn_next: bool) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            words: Vec::new(),
+            avg_logprob: 0.0,
+            no_speech_prob: 0.0,
+            temperature: 0.0,
+            speaker_turn_next,
+        }
+    }
+
+    fn dialogue() -> Transcript {
+        Transcript {
+            segments: vec![
+                segment(0.0, 1.5, " Привет.", true),
+                segment(1.5, 3.25, " Здравствуйте.", false),
+                segment(3.25, 61.0, " Как дела?", false),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_text_export_marks_turns() {
+        assert_eq!(to_text(&dialogue()), "- Привет.\n- Здравствуйте. Как дела?\n");
+
+        let monologue = Transcript { segments: vec![segment(0.0, 1.0, " Раз два.", false)], ..Default::default() };
+        assert_eq!(to_text(&monologue), "Раз два.\n");
+    }
+
+    #[test]
+    fn test_srt_export() {
+        let srt = to_srt(&dialogue());
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:01,500\nПривет. [SPEAKER_TURN]\n\n2\n"));
+        assert!(srt.contains("3\n00:00:03,250 --> 00:01:01,000\nКак дела?\n"));
+    }
+
+    #[test]
+    fn test_save_keeps_both_recordings() {
+        let dir = std::env::temp_dir().join(format!("voice-agent-export-{}", std::process::id()));
+        let first = save(&dir, &dialogue()).unwrap();
+        let second = save(&dir, &dialogue()).unwrap();
+
+        assert_ne!(first, second);
+        assert!(second.with_extension("srt").is_file());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,106 @@
+//! User glossary of domain terms (product names, surnames).
+//! Terms are put into Whisper's initial prompt and can be boosted while
+//! decoding. The file lives at ~/.config/voice-agent/glossary.txt, one
+//! term per line, `#` starts a comment.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::text::normalize_phrase;
+
+/// Glossary settings
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlossaryOptions {
+    /// Glossary file (default: glossary.txt in the config dir, if present)
+    pub path: Option<PathBuf>,
+    /// Logit bias added to glossary tokens while decoding, 0 disables
+    /// boosting (default: 0.0; 1.0-3.0 is a sensible range)
+    pub boost: f32,
+}
+
+impl GlossaryOptions {
+    /// Load the configured glossary, `None` when there is none
+    pub fn load(&self) -> anyhow::Result<Option<Glossary>> {
+        match &self.path {
+            Some(path) => Glossary::load(path).map(Some),
+            None => match config::config_dir().map(|d| d.join("glossary.txt")) {
+                Some(path) if path.is_file() => Glossary::load(&path).map(Some),
+                _ => Ok(None),
+            },
+        }
+    }
+}
+
+/// List of terms Whisper should recognize
+#[derive(Clone, Debug, Default)]
+pub struct Glossary {
+    terms: Vec<String>,
+}
+
+impl Glossary {
+    /// Parse glossary text: one term per line, blank lines and `#` comments skipped
+    pub fn parse(text: &str) -> Self {
+        let mut terms: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let term = line.split('#').next().unwrap_or("").trim();
+            if !term.is_empty() && !terms.iter().any(|t| t == term) {
+                terms.push(term.to_string());
+            }
+        }
+        Self { terms }
+    }
+
+    /// Read a glossary file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read glossary {}: {}", path.display(), e))?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Prompt text listing the terms, most important first
+    pub fn prompt(&self) -> String {
+        if self.terms.is_empty() {
+            return String::new();
+        }
+        format!("{}.", self.terms.join(", "))
+    }
+}
+
+/// Whether `text` contains `term` as whole words, ignoring case and punctuation
+pub fn contains_term(text: &str, term: &str) -> bool {
+    let term = normalize_phrase(term);
+    !term.is_empty() && format!(" {} ", normalize_phrase(text)).contains(&format!(" {} ", term))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_glossary() {
+        let glossary = Glossary::parse("# products\nВойсАгент\n\n  Кузнецов  # surname\nВойсАгент\nWhisper\n");
+        assert_eq!(glossary.terms(), ["ВойсАгент", "Кузнецов", "Whisper"]);
+        assert_eq!(glossary.prompt(), "ВойсАгент, Кузнецов, Whisper.");
+        assert!(Glossary::parse("# nothing\n").is_empty());
+        assert_eq!(Glossary::default().prompt(), "");
+    }
+
+    #[test]
+    fn test_contains_term() {
+        assert!(contains_term(" Позвоните Кузнецову и Кузнецов ответит.", "кузнецов"));
+        assert!(contains_term("Запусти Voice-Agent!", "voice agent"));
+        assert!(!contains_term(" Кузнецова нет.", "Кузнецов"));
+        assert!(!contains_term("что угодно", ""));
+    }
+}
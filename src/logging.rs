@@ -0,0 +1,194 @@
+//! Log file for the app and whisper.cpp diagnostics.
+//! Messages go to ~/.config/voice-agent/logs/voice-agent.log, rotated at
+//! `MAX_LOG_BYTES` with `KEEP_LOGS` old files kept. With `--verbose` they are
+//! also printed to stderr and debug messages are included.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+
+/// Size at which the log file is rotated
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Rotated files kept as voice-agent.log.1, .2, ...
+const KEEP_LOGS: usize = 3;
+
+/// whisper.cpp's error when the encoder-begin callback stops `full`, which
+/// the chunk probe does on purpose to prepare the encoder
+const ENCODER_STOPPED: &str = "encoder_begin_callback returned false - aborting";
+
+/// Log file that starts over once it grows past its size limit
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), file, written, max_bytes })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.written + line.len() as u64 > self.max_bytes && self.written > 0 {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift voice-agent.log.N to .N+1, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        let numbered = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        fs::remove_file(numbered(KEEP_LOGS)).ok();
+        for n in (1..KEEP_LOGS).rev() {
+            fs::rename(numbered(n), numbered(n + 1)).ok();
+        }
+        fs::rename(&self.path, numbered(1))?;
+
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+struct Logger {
+    file: Option<Mutex<RotatingFile>>,
+    level: LevelFilter,
+    verbose: bool,
+}
+
+/// Seconds since the epoch with milliseconds, without a date dependency
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
+/// Module that logged the message; whisper.cpp messages come through whisper-rs
+fn source(target: &str) -> &str {
+    if target.starts_with("whisper_rs") {
+        "whisper.cpp"
+    } else {
+        target
+    }
+}
+
+/// Level a message is logged at: whisper.cpp's error for the probe's
+/// intended encoder stop is only debug output
+fn level_of(level: Level, target: &str, message: &str) -> Level {
+    if source(target) == "whisper.cpp" && message.contains(ENCODER_STOPPED) {
+        Level::Debug
+    } else {
+        level
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        let level = level_of(record.level(), record.target(), &message);
+        if message.is_empty() || level > self.level {
+            return;
+        }
+
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let line = format!("{} {:<5} [{}] {}\n", timestamp(), level, source(record.target()), message);
+                file.write_line(&line).ok();
+            }
+        }
+        if self.verbose {
+            // The UI runs in raw mode, so return to the line start explicitly
+            eprint!("\r[{} {}] {}\r\n", level, source(record.target()), message);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                file.file.flush().ok();
+            }
+        }
+    }
+}
+
+/// Log file path (~/.config/voice-agent/logs/voice-agent.log)
+pub fn log_path() -> Option<PathBuf> {
+    config::config_dir().map(|p| p.join("logs").join("voice-agent.log"))
+}
+
+/// Install the logger and route whisper.cpp's messages into it. Returns the
+/// log file path, or `None` when the file could not be opened; logging to
+/// stderr with `verbose` still works then.
+pub fn init(verbose: bool) -> Option<PathBuf> {
+    let path = log_path();
+    let file = path.as_ref().and_then(|path| {
+        fs::create_dir_all(path.parent()?).ok()?;
+        RotatingFile::open(path, MAX_LOG_BYTES).ok()
+    });
+    let path = path.filter(|_| file.is_some());
+
+    let level = if verbose { LevelFilter::Debug } else { LevelFilter::Info };
+    let logger = Logger { file: file.map(Mutex::new), level, verbose };
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+    whisper_rs::install_whisper_log_trampoline();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("voice-agent-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.log");
+
+        let mut file = RotatingFile::open(&path, 20).unwrap();
+        for i in 0..6 {
+            file.write_line(&format!("line {:02} .......\n", i)).unwrap();
+        }
+
+        // One 16-byte line per file; only KEEP_LOGS rotated files remain
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 05 .......\n");
+        assert_eq!(fs::read_to_string(dir.join("test.log.1")).unwrap(), "line 04 .......\n");
+        assert!(dir.join(format!("test.log.{}", KEEP_LOGS)).exists());
+        assert!(!dir.join(format!("test.log.{}", KEEP_LOGS + 1)).exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_source_names_whisper_cpp() {
+        assert_eq!(source("whisper_rs::whisper_sys_log"), "whisper.cpp");
+        assert_eq!(source("voice_agent::whisper"), "voice_agent::whisper");
+    }
+
+    #[test]
+    fn test_probe_encoder_stop_is_debug() {
+        let stopped = "whisper_full_with_state: encoder_begin_callback returned false - aborting\n";
+        assert_eq!(level_of(Level::Error, "whisper_rs::whisper_sys_log", stopped), Level::Debug);
+        let failed = "whisper_full_with_state: failed to encode";
+        assert_eq!(level_of(Level::Error, "whisper_rs::whisper_sys_log", failed), Level::Error);
+        assert_eq!(level_of(Level::Error, "voice_agent::whisper", stopped), Level::Error);
+    }
+}
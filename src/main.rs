@@ -1,102 +1,214 @@
 mod audio;
 mod audio_processor;
+mod bench;
 mod calibration;
+mod cancel;
+mod cli;
+mod codeswitch;
+mod config;
+mod export;
+mod glossary;
+mod logging;
+mod models;
+mod prompt;
+mod streaming;
+mod suppress;
+mod text;
+mod transcriber;
 mod whisper;
 mod ui;
+mod worker;
 
 use audio::AudioRecorder;
-use audio_processor::AudioProcessor;
 use calibration::{run_calibration, VoiceProfile};
+use cli::CliArgs;
+use config::Config;
+use ui::Action;
 use whisper::WhisperModel;
+use worker::{Job, Worker};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::io::{self, Write};
 use std::env;
+use std::path::{Path, PathBuf};
 
 fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let force_calibrate = args.iter().any(|a| a == "--calibrate" || a == "-c");
+    let args = CliArgs::parse(env::args())?;
+    let log_path = logging::init(args.verbose);
+    let mut config = Config::load()?;
+    if args.threads.is_some() {
+        config.inference.threads = args.threads;
+    }
+    if let Some(parallel) = args.parallel {
+        config.inference.parallel_chunks = parallel;
+    }
+    if args.stream {
+        config.stream.enabled = true;
+    }
+    if args.diarize {
+        config.diarize = true;
+    }
+    if args.code_switch {
+        config.code_switch.enabled = true;
+    }
+    if let Some(dir) = &args.export {
+        config.export_dir = Some(PathBuf::from(dir));
+    }
+    if let Some(path) = &args.glossary {
+        config.glossary.path = Some(PathBuf::from(path));
+    }
+
+    // `models`: list installed models
+    if args.list_models {
+        return models::list_models();
+    }
+
+    // Benchmarks: `--bench [model.bin ...]`, `--bench-states [model.bin ...]`
+    let bench_models = |models: Vec<String>| -> anyhow::Result<Vec<PathBuf>> {
+        if models.is_empty() {
+            Ok(vec![config::resolve_model_path(args.model.as_deref(), &config)?])
+        } else {
+            Ok(models.into_iter().map(PathBuf::from).collect())
+        }
+    };
+    if let Some(models) = args.bench.clone() {
+        return bench::run_chunk_benchmark(&bench_models(models)?, &config.inference);
+    }
+    if let Some(models) = args.bench_states.clone() {
+        return bench::run_state_benchmark(&bench_models(models)?, &config.inference);
+    }
+
+    let model_path = config::resolve_model_path(args.model.as_deref(), &config)?;
+
+    if config.verify_models {
+        models::verify_checksum(&model_path)?;
+    }
 
     // Initialize model once at startup
-    println!("Loading model...");
-    let mut whisper_model = WhisperModel::new("models/ggml-base.bin")?;
-    println!("Model loaded!");
+    println!("Loading model {}...", model_path.display());
+    let mut whisper_model = WhisperModel::new(&model_path, config.inference.clone())?;
+    println!("Model loaded! ({} threads)", config.inference.threads());
+    if let Some(path) = &log_path {
+        println!("📄 Log: {}", path.display());
+    }
+
+    // Session language (CLI) wins over the profile's, which wins over config
+    if let Some(language) = args.language.as_deref().or(config.language.as_deref()) {
+        whisper_model.set_language(language)?;
+    }
 
     let recorder = AudioRecorder::new();
 
-    // Handle calibration
-    if force_calibrate || !VoiceProfile::exists() {
+    // Handle calibration; the glossary evaluation never records
+    if args.eval_glossary.is_none() && (args.calibrate || !VoiceProfile::exists()) {
         if !VoiceProfile::exists() {
             println!("\n⚠️  No voice profile found. Starting calibration...");
         }
-        let profile = run_calibration(&whisper_model, &recorder)?;
+        let profile = run_calibration(&whisper_model, &recorder, args.language.as_deref())?;
         whisper_model.set_calibration_prompt(&profile.prompt);
     } else if let Some(profile) = VoiceProfile::load() {
         println!("✅ Voice profile loaded");
         whisper_model.set_calibration_prompt(&profile.prompt);
+        if let (None, Some(language)) = (&args.language, &profile.language) {
+            whisper_model.set_language(language)?;
+        }
+    }
+    println!("🌐 Language: {}", whisper_model.language());
+
+    whisper_model.set_translate(args.translate || config.translate)?;
+    whisper_model.set_decode_options(config.decode.clone())?;
+    whisper_model.set_filter_options(config.filter.clone());
+    whisper_model.set_suppress_options(config.suppress.clone())?;
+    whisper_model.set_prompt_options(config.prompt.clone());
+    if let Some(glossary) = config.glossary.load()? {
+        println!("📖 Glossary: {} terms", glossary.terms().len());
+        whisper_model.set_glossary(Some(&glossary), config.glossary.boost)?;
+    }
+    whisper_model.set_keep_original(args.show_original || config.show_original);
+    whisper_model.set_diarize(config.diarize);
+    let dictionary = config.code_switch.load_dictionary()?;
+    if let Some(dictionary) = &dictionary {
+        println!("🔤 Transliterations: {} entries", dictionary.len());
+    }
+    let code_switch = whisper_model.set_code_switch(config.code_switch.clone(), dictionary)?;
+    if config.code_switch.enabled && !code_switch {
+        println!("⚠️  Code-switching needs a session language (--language ru), it is off with auto");
+    }
+
+    // Glossary evaluation: `--eval-glossary <fixtures dir>`, with the model
+    // set up as for dictation
+    if let Some(fixtures) = &args.eval_glossary {
+        return bench::run_glossary_eval(whisper_model, Path::new(fixtures), config.glossary.boost);
     }
 
     let recording = Arc::new(AtomicBool::new(false));
-    
+    let streaming = config.stream.enabled;
+
+    // The worker owns the model; transcriptions queue up while recording goes on
+    let worker = Worker::spawn(whisper_model, config);
+    let backlog = worker.backlog();
+
     // We keep the stream in a mutable option to drop it (stop it) when toggling off
     let mut stream = None;
 
-    ui::run_ui({
+    ui::run_ui(move || !backlog.is_empty(), {
         let recording = recording.clone();
-        // Move whisper_model into the closure
-        move || {
-            // Toggle logic
-            if !recording.load(Ordering::SeqCst) {
-                // START
-                print!("\r🎙  Recording... (Press SPACE to stop)   ");
-                io::stdout().flush().unwrap();
-                
-                stream = Some(recorder.start());
-                recording.store(true, Ordering::SeqCst);
-            } else {
-                // STOP
-                print!("\r⏹  Processing...                        ");
-                io::stdout().flush().unwrap();
-                
-                // Drop the stream to stop capturing
-                drop(stream.take());
-                
-                // Get audio
-                let audio = recorder.stop();
-                recording.store(false, Ordering::SeqCst);
-
-                if audio.is_empty() {
-                    print!("\r⚠️  No audio recorded.\r\n");
+        let worker = &worker;
+        move |action| match action {
+            Action::Tick => {
+                if streaming && recording.load(Ordering::SeqCst) {
+                    worker.send(Job::StreamAudio(recorder.drain()));
+                }
+            }
+            Action::ToggleRecording => {
+                // Toggle logic
+                if !recording.load(Ordering::SeqCst) {
+                    // START
+                    print!("\r🎙  Recording... (Press SPACE to stop)   ");
                     io::stdout().flush().unwrap();
+
+                    stream = Some(recorder.start());
+                    recording.store(true, Ordering::SeqCst);
+                    if streaming {
+                        print!("\r\n");
+                        worker.send(Job::StreamStart);
+                    }
                 } else {
-                    // Process audio: trim silence, normalize, chunk
-                    let processor = AudioProcessor::default();
-                    let chunks = processor.process(&audio);
-                    
-                    if chunks.is_empty() {
-                        print!("\r⚠️  No speech detected.\r\n");
-                        io::stdout().flush().unwrap();
+                    // STOP
+                    // Drop the stream to stop capturing
+                    drop(stream.take());
+
+                    // Get audio
+                    let audio = recorder.stop();
+                    recording.store(false, Ordering::SeqCst);
+
+                    let ahead = worker.backlog().len();
+                    if ahead > 0 {
+                        print!("\r⏹  Queued behind {} recording(s)              \r\n", ahead);
                     } else {
-                        print!("\r⏳ Transcribing {} chunk(s)...\r\n", chunks.len());
-                        io::stdout().flush().unwrap();
-                        
-                        // Transcribe using chunked method with context
-                        match whisper_model.transcribe_chunks(&chunks) {
-                            Ok(text) => {
-                                print!("\r📝 RESULT: {}\r\n", text.trim());
-                                print!("\r[ SPACE ] Ready\r\n");
-                                io::stdout().flush().unwrap();
-                            }
-                            Err(e) => {
-                                eprint!("\r❌ Error: {}\r\n", e);
-                                io::stdout().flush().unwrap();
-                            }
-                        }
+                        print!("\r⏹  Processing...                        \r\n");
                     }
+                    worker.send(if streaming { Job::StreamEnd(audio) } else { Job::Transcribe(audio) });
+                    print!("\r[ SPACE ] Ready\r\n");
+                    io::stdout().flush().unwrap();
                 }
             }
+            // Settings changes wait for the queued recordings, so those keep
+            // the settings they were recorded with
+            Action::ToggleTranslate => worker.send(Job::ToggleTranslate),
+            Action::ToggleBeamSearch => worker.send(Job::ToggleBeamSearch),
+            Action::ResetContext => worker.send(Job::ResetContext),
+            Action::SwitchModel if !recording.load(Ordering::SeqCst) => worker.send(Job::SwitchModel),
+            Action::Rerun if !recording.load(Ordering::SeqCst) => worker.send(Job::Rerun),
+            Action::SwitchModel | Action::Rerun => {}
+            Action::Cancel => {
+                worker.cancel();
+                print!("\r⛔ Cancelling...\r\n");
+                io::stdout().flush().unwrap();
+            }
         }
     })?;
 
+    worker.finish();
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,275 @@
+//! Model file inspection and checksum verification.
+//! Reads the ggml header whisper.cpp expects, so a corrupted or unrelated
+//! file is reported before loading it. Checksums come from a `SHA256SUMS`
+//! manifest (`sha256sum` format) next to the models.
+
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+/// "ggml" as the little-endian magic number of whisper.cpp model files
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+
+/// Manifest file looked up in the model's directory
+pub const MANIFEST_FILE: &str = "SHA256SUMS";
+
+/// Multilingual vocabularies have at least this many tokens
+const MULTILINGUAL_VOCAB: i32 = 51865;
+
+/// Model hyperparameters from the file header
+#[derive(Debug, PartialEq)]
+pub struct ModelHeader {
+    pub n_vocab: i32,
+    pub n_audio_layer: i32,
+    pub n_text_ctx: i32,
+    pub n_mels: i32,
+    /// Weight type, quantization version removed
+    pub ftype: i32,
+}
+
+impl ModelHeader {
+    /// Parse the magic number and hyperparameters at the start of a model file
+    pub fn parse(mut reader: impl Read) -> anyhow::Result<Self> {
+        let mut read_i32 = || -> anyhow::Result<i32> {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf).map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => anyhow::anyhow!("file is truncated"),
+                _ => anyhow::anyhow!(e),
+            })?;
+            Ok(i32::from_le_bytes(buf))
+        };
+
+        let magic = read_i32()? as u32;
+        if magic != GGML_MAGIC {
+            anyhow::bail!("not a ggml Whisper model (magic {:#010x})", magic);
+        }
+
+        // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer,
+        // n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+        let mut hparams = [0i32; 11];
+        for value in &mut hparams {
+            *value = read_i32()?;
+        }
+        let header = Self {
+            n_vocab: hparams[0],
+            n_audio_layer: hparams[4],
+            n_text_ctx: hparams[5],
+            n_mels: hparams[9],
+            ftype: hparams[10] % 1000,
+        };
+        if header.n_vocab <= 0 || header.n_audio_layer <= 0 || header.n_mels <= 0 {
+            anyhow::bail!("invalid model header {:?}", header);
+        }
+        Ok(header)
+    }
+
+    /// Model size by encoder depth, as named by whisper.cpp
+    pub fn model_type(&self) -> &'static str {
+        match self.n_audio_layer {
+            4 => "tiny",
+            6 => "base",
+            12 => "small",
+            24 => "medium",
+            32 => "large",
+            _ => "unknown",
+        }
+    }
+
+    pub fn is_multilingual(&self) -> bool {
+        self.n_vocab >= MULTILINGUAL_VOCAB
+    }
+
+    /// Weight type or quantization
+    pub fn ftype_name(&self) -> &'static str {
+        match self.ftype {
+            0 => "f32",
+            1 => "f16",
+            2 => "q4_0",
+            3 => "q4_1",
+            7 => "q8_0",
+            8 => "q5_0",
+            9 => "q5_1",
+            10 => "q2_k",
+            11 => "q3_k",
+            12 => "q4_k",
+            13 => "q5_k",
+            14 => "q6_k",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Read the header of a model file
+pub fn inspect(path: &Path) -> anyhow::Result<ModelHeader> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot open model {}: {}", path.display(), e))?;
+    ModelHeader::parse(io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("Invalid model {}: {}", path.display(), e))
+}
+
+/// SHA-256 of a file as lowercase hex
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Expected hash of `file_name` in `sha256sum` output
+fn manifest_entry(manifest: &str, file_name: &str) -> Option<String> {
+    manifest.lines().find_map(|line| {
+        let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+        // sha256sum marks binary mode with '*'
+        let name = name.trim_start().trim_start_matches('*');
+        (name == file_name).then(|| hash.to_lowercase())
+    })
+}
+
+/// Checksum check result
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+    Verified,
+    /// The manifest expects another hash
+    Mismatch { expected: String, actual: String },
+    /// No manifest, or no entry for this file
+    Unlisted,
+}
+
+/// Compare a model's hash with its entry in the manifest next to it
+pub fn verify(path: &Path, actual: &str) -> Verification {
+    let expected = path
+        .parent()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .and_then(|manifest| fs::read_to_string(manifest).ok())
+        .zip(path.file_name().and_then(|n| n.to_str()))
+        .and_then(|(manifest, name)| manifest_entry(&manifest, name));
+
+    match expected {
+        None => Verification::Unlisted,
+        Some(expected) if expected == actual => Verification::Verified,
+        Some(expected) => Verification::Mismatch { expected, actual: actual.to_string() },
+    }
+}
+
+/// Fail if the manifest lists the model with another hash
+pub fn verify_checksum(path: &Path) -> anyhow::Result<()> {
+    if let Verification::Mismatch { expected, actual } = verify(path, &sha256_file(path)?) {
+        anyhow::bail!(
+            "Checksum mismatch for {}: expected {}, got {}. Re-download the model.",
+            path.display(),
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Print installed models with their metadata (`models` command)
+pub fn list_models() -> anyhow::Result<()> {
+    let models: Vec<PathBuf> = config::installed_models();
+    if models.is_empty() {
+        println!("No models found in ./models or the data dir.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<36} {:>8} {:>7} {:>6} {:>6} {:>8} {:>9}  sha256",
+        "model", "size MB", "type", "lang", "vocab", "weights", "checksum"
+    );
+    for path in &models {
+        let name = path.display().to_string();
+        let size = fs::metadata(path).map(|m| m.len() as f64 / 1_048_576.0).unwrap_or(0.0);
+        match inspect(path) {
+            Ok(header) => {
+                let hash = sha256_file(path)?;
+                let checksum = match verify(path, &hash) {
+                    Verification::Verified => "ok",
+                    Verification::Mismatch { .. } => "MISMATCH",
+                    Verification::Unlisted => "-",
+                };
+                println!(
+                    "{:<36} {:>8.0} {:>7} {:>6} {:>6} {:>8} {:>9}  {}",
+                    name,
+                    size,
+                    header.model_type(),
+                    if header.is_multilingual() { "multi" } else { "en" },
+                    header.n_vocab,
+                    header.ftype_name(),
+                    checksum,
+                    hash
+                );
+            }
+            Err(e) => println!("{:<36} {:>8.0}  {}", name, size, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_bytes(magic: u32, hparams: [i32; 11]) -> Vec<u8> {
+        let mut bytes = magic.to_le_bytes().to_vec();
+        for value in hparams {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_header() {
+        // ggml-base.bin, q5_1 with quantization version 2
+        let bytes = header_bytes(GGML_MAGIC, [51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 2009]);
+        let header = ModelHeader::parse(bytes.as_slice()).unwrap();
+
+        assert_eq!(header.model_type(), "base");
+        assert!(header.is_multilingual());
+        assert_eq!(header.ftype_name(), "q5_1");
+        assert_eq!(header.n_text_ctx, 448);
+
+        let english = header_bytes(GGML_MAGIC, [51864, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 1]);
+        let header = ModelHeader::parse(english.as_slice()).unwrap();
+        assert_eq!(header.model_type(), "tiny");
+        assert!(!header.is_multilingual());
+        assert_eq!(header.ftype_name(), "f16");
+    }
+
+    #[test]
+    fn test_parse_bad_header() {
+        let html = b"<!DOCTYPE html><html>Not Found</html>".to_vec();
+        let err = ModelHeader::parse(html.as_slice()).unwrap_err().to_string();
+        assert!(err.contains("not a ggml Whisper model"), "{}", err);
+
+        let bytes = header_bytes(GGML_MAGIC, [51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 1]);
+        let err = ModelHeader::parse(&bytes[..20]).unwrap_err().to_string();
+        assert!(err.contains("truncated"), "{}", err);
+    }
+
+    #[test]
+    fn test_manifest_entry() {
+        let manifest = "ABC123  ggml-base.bin\ndef456 *ggml-small.bin\n\n";
+        assert_eq!(manifest_entry(manifest, "ggml-base.bin"), Some("abc123".to_string()));
+        assert_eq!(manifest_entry(manifest, "ggml-small.bin"), Some("def456".to_string()));
+        assert_eq!(manifest_entry(manifest, "ggml-tiny.bin"), None);
+    }
+
+    #[test]
+    fn test_verify_against_manifest() {
+        let dir = std::env::temp_dir().join(format!("voice-agent-models-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let model = dir.join("ggml-test.bin");
+        fs::write(&model, b"model").unwrap();
+        let hash = sha256_file(&model).unwrap();
+
+        assert_eq!(verify(&model, &hash), Verification::Unlisted);
+        fs::write(dir.join(MANIFEST_FILE), format!("{}  ggml-test.bin\n", hash)).unwrap();
+        assert_eq!(verify(&model, &hash), Verification::Verified);
+        assert!(matches!(verify(&model, "0000"), Verification::Mismatch { .. }));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
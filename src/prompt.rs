@@ -0,0 +1,210 @@
+//! Initial prompt construction for Whisper.
+//! Prompts are cut to a token budget on word boundaries, so a multi-byte
+//! character is never split.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Token budget of the initial prompt
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptOptions {
+    /// Prompt tokens in total, capped at the model's limit (default: 224)
+    pub max_tokens: usize,
+    /// Tokens reserved for the previous chunk's text; calibration and
+    /// glossary text get the rest (default: 96)
+    pub context_tokens: usize,
+    /// Recent recordings whose text prompts the next one, 0 disables
+    /// session context (default: 3)
+    pub session_utterances: usize,
+}
+
+impl Default for PromptOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 224,
+            context_tokens: 96,
+            session_utterances: 3,
+        }
+    }
+}
+
+/// Byte offsets where words start, in order
+fn word_starts(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .filter(|&(i, c)| {
+            !c.is_whitespace() && text[..i].chars().next_back().is_none_or(char::is_whitespace)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Longest run of whole words from the end of `text` within `budget`.
+/// Counts grow with the text, so the cut is binary-searched and `count`
+/// runs a logarithmic number of times.
+pub fn tail_within(text: &str, budget: usize, count: impl Fn(&str) -> usize) -> &str {
+    let text = text.trim();
+    let starts = word_starts(text);
+    let first = starts.partition_point(|&start| count(&text[start..]) > budget);
+    starts.get(first).map_or("", |&start| &text[start..])
+}
+
+/// Longest run of whole words from the start of `text` within `budget`
+pub fn head_within(text: &str, budget: usize, count: impl Fn(&str) -> usize) -> &str {
+    let text = text.trim();
+    let mut ends: Vec<usize> = word_starts(text)
+        .into_iter()
+        .skip(1)
+        .map(|start| text[..start].trim_end().len())
+        .collect();
+    if !text.is_empty() {
+        ends.push(text.len());
+    }
+    let fitting = ends.partition_point(|&end| count(&text[..end]) <= budget);
+    fitting.checked_sub(1).map_or("", |last| &text[..ends[last]])
+}
+
+impl PromptOptions {
+    /// Build a prompt from fixed text (calibration, glossary), kept from its
+    /// start, and the previous text, kept from its end and placed last so it
+    /// sits next to the audio. Budget the fixed text leaves unused goes to
+    /// the previous text. `count` returns the token count of a string.
+    pub fn build(&self, fixed: &[&str], previous: &str, count: impl Fn(&str) -> usize) -> String {
+        let fixed: Vec<&str> = fixed.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+        let fixed_budget = self.max_tokens.saturating_sub(self.context_tokens);
+        let fixed = head_within(&fixed.join(" "), fixed_budget, &count).to_string();
+
+        let context_budget = self.max_tokens.saturating_sub(count(&fixed));
+        let context = tail_within(previous, context_budget, &count);
+
+        match (fixed.is_empty(), context.is_empty()) {
+            (_, true) => fixed,
+            (true, false) => context.to_string(),
+            (false, false) => format!("{} {}", fixed, context),
+        }
+    }
+}
+
+/// Text of recent recordings, carried into the next recording's prompt so
+/// names and spellings stay consistent across a session
+pub struct SessionContext {
+    utterances: VecDeque<String>,
+    limit: usize,
+}
+
+impl SessionContext {
+    /// Keep the text of the last `limit` recordings
+    pub fn new(limit: usize) -> Self {
+        Self { utterances: VecDeque::new(), limit }
+    }
+
+    /// Add a recording's text, dropping the oldest beyond the limit;
+    /// returns whether the text was added
+    pub fn push(&mut self, text: &str) -> bool {
+        let text = text.trim();
+        if text.is_empty() || self.limit == 0 {
+            return false;
+        }
+        self.utterances.push_back(text.to_string());
+        while self.utterances.len() > self.limit {
+            self.utterances.pop_front();
+        }
+        true
+    }
+
+    /// Remove up to `n` latest entries, e.g. to transcribe their recording
+    /// again; returns them oldest first
+    pub fn pop_last(&mut self, n: usize) -> Vec<String> {
+        let keep = self.utterances.len().saturating_sub(n);
+        self.utterances.split_off(keep).into()
+    }
+
+    pub fn clear(&mut self) {
+        self.utterances.clear();
+    }
+
+    /// Recent text, oldest first
+    pub fn text(&self) -> String {
+        self.utterances.iter().map(String::as_str).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One token per word, for predictable budgets
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_tail_and_head_keep_whole_words() {
+        let text = "Раз два  три четыре";
+        assert_eq!(tail_within(text, 2, words), "три четыре");
+        assert_eq!(head_within(text, 2, words), "Раз два");
+        assert_eq!(tail_within(text, 10, words), text);
+        assert_eq!(head_within(text, 0, words), "");
+
+        // Budget in bytes: never cut inside a two-byte character
+        assert_eq!(tail_within("привет мир", 7, str::len), "мир");
+        assert_eq!(head_within("привет мир", 13, str::len), "привет");
+    }
+
+    #[test]
+    fn test_cut_counts_logarithmically() {
+        let text = "слово ".repeat(4096);
+        let calls = std::cell::Cell::new(0);
+        let counting = |t: &str| {
+            calls.set(calls.get() + 1);
+            words(t)
+        };
+
+        assert_eq!(words(tail_within(&text, 100, counting)), 100);
+        assert!(calls.get() <= 14, "{} counts", calls.get());
+        calls.set(0);
+        assert_eq!(words(head_within(&text, 100, counting)), 100);
+        assert!(calls.get() <= 14, "{} counts", calls.get());
+    }
+
+    #[test]
+    fn test_build_prompt_budget() {
+        let options = PromptOptions { max_tokens: 6, context_tokens: 2, ..Default::default() };
+        let calibration = "один два три четыре пять шесть";
+
+        // Fixed text is capped so the context keeps its share
+        assert_eq!(
+            options.build(&[calibration], "семь восемь девять", words),
+            "один два три четыре восемь девять"
+        );
+        // Short fixed text leaves more room for the context
+        assert_eq!(
+            options.build(&["один"], "два три четыре пять шесть семь восемь", words),
+            "один четыре пять шесть семь восемь"
+        );
+        assert_eq!(options.build(&[], "", words), "");
+        assert_eq!(options.build(&["  ", "один"], "", words), "один");
+    }
+
+    #[test]
+    fn test_session_context_rolls() {
+        let mut context = SessionContext::new(2);
+        context.push(" Звонила Ксения.");
+        assert!(!context.push("  "));
+        context.push(" Ксения просила перезвонить.");
+        context.push(" Перезвоню завтра.");
+        assert_eq!(context.text(), "Ксения просила перезвонить. Перезвоню завтра.");
+
+        assert_eq!(context.pop_last(1), ["Перезвоню завтра."]);
+        context.push(" Перезвоню завтра.");
+        assert_eq!(context.pop_last(3), ["Ксения просила перезвонить.", "Перезвоню завтра."]);
+        assert!(context.pop_last(1).is_empty());
+        context.push(" Перезвоню завтра.");
+        context.clear();
+        assert_eq!(context.text(), "");
+
+        let mut disabled = SessionContext::new(0);
+        disabled.push("текст");
+        assert_eq!(disabled.text(), "");
+    }
+}
@@ -0,0 +1,224 @@
+//! Live transcription while recording.
+//! Re-transcribes the uncommitted audio at a fixed step and commits the text
+//! once the speaker pauses, similar to whisper.cpp's `stream` example.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio_processor::{AudioProcessor, SAMPLE_RATE};
+use crate::transcriber::Transcriber;
+use crate::whisper::Transcript;
+
+/// Streaming settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamOptions {
+    /// Show partial transcripts while recording
+    pub enabled: bool,
+    /// Seconds of new audio between partial updates (default: 1.0)
+    pub step_secs: f32,
+    /// Trailing silence that commits the text (default: 0.8)
+    pub pause_secs: f32,
+    /// Longest uncommitted audio before a forced commit (default: 25.0)
+    pub max_window_secs: f32,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            step_secs: 1.0,
+            pause_secs: 0.8,
+            max_window_secs: 25.0,
+        }
+    }
+}
+
+/// Output of a streaming update
+pub enum StreamEvent {
+    /// Text of the uncommitted audio, may still change
+    Partial(String),
+    /// Stable text, the audio behind it is dropped
+    Commit(Transcript),
+}
+
+/// What to do with the pending audio on a tick
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// Not enough new audio since the last update
+    Wait,
+    /// Pending audio is silence only
+    Discard,
+    /// Re-transcribe and show a partial line
+    Partial,
+    /// Transcribe and commit
+    Commit,
+}
+
+/// Sliding-window transcriber fed with audio while recording
+pub struct LiveTranscriber {
+    options: StreamOptions,
+    processor: AudioProcessor,
+    /// Audio not committed yet
+    pending: Vec<f32>,
+    /// Length of `pending` at the last update
+    last_update: usize,
+    /// Samples recorded before `pending`, committed or discarded
+    offset: usize,
+}
+
+impl LiveTranscriber {
+    pub fn new(options: StreamOptions) -> Self {
+        Self {
+            options,
+            processor: AudioProcessor::default(),
+            pending: Vec::new(),
+            last_update: 0,
+            offset: 0,
+        }
+    }
+
+    /// Add newly captured audio
+    pub fn push(&mut self, audio: &[f32]) {
+        self.pending.extend_from_slice(audio);
+    }
+
+    fn secs_to_samples(secs: f32) -> usize {
+        (secs * SAMPLE_RATE as f32) as usize
+    }
+
+    fn next_step(&self) -> Step {
+        let step = Self::secs_to_samples(self.options.step_secs);
+        if self.pending.len() < self.last_update + step {
+            return Step::Wait;
+        }
+        if !self.processor.has_speech(&self.pending) {
+            return Step::Discard;
+        }
+        if self.processor.ends_with_pause(&self.pending, self.options.pause_secs)
+            || self.pending.len() >= Self::secs_to_samples(self.options.max_window_secs)
+        {
+            return Step::Commit;
+        }
+        Step::Partial
+    }
+
+    /// Update from the pending audio; call periodically while recording
+    pub fn tick<T: Transcriber + ?Sized>(&mut self, model: &T) -> anyhow::Result<Option<StreamEvent>> {
+        match self.next_step() {
+            Step::Wait => Ok(None),
+            Step::Discard => {
+                self.offset += self.pending.len();
+                self.pending.clear();
+                self.last_update = 0;
+                Ok(None)
+            }
+            Step::Partial => {
+                self.last_update = self.pending.len();
+                let transcript = model.transcribe_chunks(&self.processor.process(&self.pending))?;
+                Ok(Some(StreamEvent::Partial(transcript.text().trim().to_string())))
+            }
+            Step::Commit => Ok(self.commit(model)?.map(StreamEvent::Commit)),
+        }
+    }
+
+    /// Commit whatever is pending, e.g. when recording stops. Timestamps
+    /// are from the start of the recording.
+    pub fn commit<T: Transcriber + ?Sized>(&mut self, model: &T) -> anyhow::Result<Option<Transcript>> {
+        let mut chunks = self.processor.process(&self.pending);
+        let offset_secs = self.offset as f32 / SAMPLE_RATE as f32;
+        self.offset += self.pending.len();
+        self.pending.clear();
+        self.last_update = 0;
+
+        if chunks.is_empty() {
+            return Ok(None);
+        }
+        for chunk in &mut chunks {
+            chunk.offset_secs += offset_secs;
+        }
+        model.transcribe_chunks(&chunks).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcriber::fake::ScriptedTranscriber;
+
+    fn secs(n: f32) -> Vec<f32> {
+        vec![0.0; LiveTranscriber::secs_to_samples(n)]
+    }
+
+    fn speech(n: f32) -> Vec<f32> {
+        vec![0.5; LiveTranscriber::secs_to_samples(n)]
+    }
+
+    #[test]
+    fn test_stream_steps() {
+        let mut live = LiveTranscriber::new(StreamOptions::default());
+
+        live.push(&speech(0.5));
+        assert_eq!(live.next_step(), Step::Wait);
+
+        live.push(&speech(1.0));
+        assert_eq!(live.next_step(), Step::Partial);
+
+        live.last_update = live.pending.len();
+        live.push(&secs(1.0));
+        assert_eq!(live.next_step(), Step::Commit);
+    }
+
+    #[test]
+    fn test_stream_discards_silence() {
+        let mut live = LiveTranscriber::new(StreamOptions::default());
+        live.push(&secs(2.0));
+        assert_eq!(live.next_step(), Step::Discard);
+    }
+
+    #[test]
+    fn test_stream_forces_commit_on_long_window() {
+        let mut live = LiveTranscriber::new(StreamOptions {
+            max_window_secs: 3.0,
+            ..Default::default()
+        });
+        live.push(&speech(3.0));
+        assert_eq!(live.next_step(), Step::Commit);
+    }
+
+    #[test]
+    fn test_stream_partial_then_commit() {
+        let transcriber = ScriptedTranscriber::new(&[" Привет", " Привет, мир."]);
+        let mut live = LiveTranscriber::new(StreamOptions::default());
+
+        live.push(&speech(1.5));
+        match live.tick(&transcriber).unwrap() {
+            Some(StreamEvent::Partial(text)) => assert_eq!(text, "Привет"),
+            _ => panic!("expected a partial transcript"),
+        }
+        assert!(live.tick(&transcriber).unwrap().is_none());
+
+        live.push(&secs(1.0));
+        match live.tick(&transcriber).unwrap() {
+            Some(StreamEvent::Commit(transcript)) => assert_eq!(transcript.text(), " Привет, мир."),
+            _ => panic!("expected a commit"),
+        }
+        assert!(live.pending.is_empty());
+        assert!(live.commit(&transcriber).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_commit_times_from_recording_start() {
+        let transcriber = ScriptedTranscriber::new(&[" Раз.", " Два."]);
+        let mut live = LiveTranscriber::new(StreamOptions::default());
+
+        live.push(&secs(2.0));
+        assert!(live.tick(&transcriber).unwrap().is_none(), "silence is discarded");
+        live.push(&speech(1.0));
+        let first = live.commit(&transcriber).unwrap().unwrap();
+        assert!(first.segments[0].start >= 2.0);
+
+        live.push(&speech(1.0));
+        let second = live.commit(&transcriber).unwrap().unwrap();
+        assert!(second.segments[0].start >= 3.0);
+    }
+}
@@ -0,0 +1,335 @@
+//! Suppressing non-speech output.
+//! Settings for whisper.cpp's blank and non-speech token suppression, user
+//! tokens banned while decoding, and post-processing that strips bracketed
+//! annotations ("[музыка]", "(смеется)") and user patterns from the text.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::whisper::Segment;
+
+/// Token suppression and text clean-up settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuppressOptions {
+    /// Suppress blank output at the start of a segment (default: true)
+    pub suppress_blank: bool,
+    /// Suppress tokens of non-speech annotations like "[" or "♪" (default: true)
+    pub non_speech_tokens: bool,
+    /// Remove text in square or round brackets and music symbols (default: true)
+    pub strip_annotations: bool,
+    /// Words never decoded; each must be a single token of the model
+    pub tokens: Vec<String>,
+    /// Text removed from transcripts, `*` matches any run of characters;
+    /// a trailing `*` runs to the end of the segment
+    pub patterns: Vec<String>,
+}
+
+impl Default for SuppressOptions {
+    fn default() -> Self {
+        Self {
+            suppress_blank: true,
+            non_speech_tokens: true,
+            strip_annotations: true,
+            tokens: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Symbols Whisper uses to mark music
+const MUSIC_SYMBOLS: &[char] = &['♪', '♫', '♬'];
+
+/// Left where text was removed, so tidying knows which punctuation the
+/// removal orphaned
+const REMOVED: &str = "\0";
+
+/// Change in bracket depth caused by `c`
+fn bracket_step(c: char) -> i32 {
+    match c {
+        '[' | '(' => 1,
+        ']' | ')' => -1,
+        _ => 0,
+    }
+}
+
+/// Remove bracketed text and music symbols, starting at bracket depth
+/// `depth`; returns the remaining text, marked with `REMOVED` where an
+/// annotation started or ended, and the depth at its end
+fn strip_from(text: &str, mut depth: i32) -> (String, i32) {
+    let mut kept = String::with_capacity(text.len());
+    for c in text.chars() {
+        let step = bracket_step(c);
+        if step != 0 {
+            let was = depth;
+            depth = (depth + step).max(0);
+            if (was == 0) != (depth == 0) {
+                kept.push_str(REMOVED);
+            }
+        } else if depth == 0 {
+            if MUSIC_SYMBOLS.contains(&c) {
+                kept.push_str(REMOVED);
+            } else {
+                kept.push(c);
+            }
+        }
+    }
+    (kept, depth)
+}
+
+/// Drop the `REMOVED` marks and the punctuation left alone next to them,
+/// then collapse runs of whitespace, keeping one leading space as Whisper
+/// segments have it; text where nothing was removed is returned as is
+fn tidy(text: &str, leading_space: bool) -> String {
+    if !text.contains(REMOVED) {
+        return text.to_string();
+    }
+    let words: Vec<String> = text
+        .split_whitespace()
+        .filter_map(|w| {
+            let kept = w.replace(REMOVED, "");
+            let orphaned = w.contains(REMOVED) && !kept.chars().any(char::is_alphanumeric);
+            (!orphaned && !kept.is_empty()).then_some(kept)
+        })
+        .collect();
+    let joined = words.join(" ");
+    if leading_space && !joined.is_empty() {
+        format!(" {}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Byte ranges of the matches of a `*` wildcard pattern in `text`, left
+/// to right; a trailing `*` runs to the end of `text`
+fn find_pattern(text: &str, pattern: &str) -> Vec<Range<usize>> {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut found = Vec::new();
+    if parts.iter().all(|p| p.is_empty()) {
+        return found;
+    }
+
+    let mut offset = 0;
+    // Leftmost match: the first part, then each further part after it
+    'search: while let Some(i) = text[offset..].find(parts[0]) {
+        let start = offset + i;
+        let mut end = start + parts[0].len();
+        for part in &parts[1..] {
+            match text[end..].find(part) {
+                Some(i) => end += i + part.len(),
+                None => break 'search,
+            }
+        }
+        if pattern.ends_with('*') {
+            end = text.len();
+        }
+        if end == start {
+            break;
+        }
+        found.push(start..end);
+        offset = end;
+    }
+    found
+}
+
+/// Replace every match of a `*` wildcard pattern in `text` with `marker`
+fn remove_pattern(text: &str, pattern: &str, marker: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut kept = 0;
+    for found in find_pattern(text, pattern) {
+        result.push_str(&text[kept..found.start]);
+        result.push_str(marker);
+        kept = found.end;
+    }
+    result.push_str(&text[kept..]);
+    result
+}
+
+/// Remove the matches of a pattern from words, matching it against the
+/// words joined by spaces as in the segment text, so a match can span
+/// words; each word loses the part a match covers, marked with `REMOVED`
+fn remove_pattern_words(words: &[String], pattern: &str) -> Vec<String> {
+    let joined = words.join(" ");
+    let matches = find_pattern(&joined, pattern);
+    let mut start = 0;
+    words
+        .iter()
+        .map(|word| {
+            let end = start + word.len();
+            let mut kept = String::with_capacity(word.len());
+            let mut at = start;
+            for found in matches.iter().filter(|m| m.start < end && m.end > start) {
+                kept.push_str(&joined[at..found.start.max(start)]);
+                kept.push_str(REMOVED);
+                at = found.end.min(end);
+            }
+            kept.push_str(&joined[at..end]);
+            start = end + 1;
+            kept
+        })
+        .collect()
+}
+
+impl SuppressOptions {
+    /// Clean one piece of text
+    fn clean_text(&self, text: &str) -> String {
+        let mut cleaned = if self.strip_annotations {
+            strip_from(text, 0).0
+        } else {
+            text.to_string()
+        };
+        for pattern in &self.patterns {
+            cleaned = remove_pattern(&cleaned, pattern, REMOVED);
+        }
+        cleaned
+    }
+
+    /// Strip annotations and patterns from segments and their words,
+    /// dropping segments left without text
+    pub fn clean(&self, segments: Vec<Segment>) -> Vec<Segment> {
+        if !self.strip_annotations && self.patterns.is_empty() {
+            return segments;
+        }
+
+        segments
+            .into_iter()
+            .filter_map(|mut segment| {
+                // Annotations span several words of a segment, so the depth
+                // carries over from word to word, but not into the next segment
+                let mut depth = 0;
+                segment.text = tidy(&self.clean_text(&segment.text), segment.text.starts_with(' '));
+                let mut words: Vec<_> = std::mem::take(&mut segment.words)
+                    .into_iter()
+                    .filter_map(|mut word| {
+                        if self.strip_annotations {
+                            let (text, end_depth) = strip_from(&word.text, depth);
+                            depth = end_depth;
+                            word.text = text;
+                        }
+                        (!word.text.is_empty()).then_some(word)
+                    })
+                    .collect();
+                // Patterns may span words, as they do in the segment text
+                for pattern in &self.patterns {
+                    let texts: Vec<String> = words.iter().map(|w| w.text.clone()).collect();
+                    for (word, text) in words.iter_mut().zip(remove_pattern_words(&texts, pattern)) {
+                        word.text = text;
+                    }
+                }
+                words.retain_mut(|word| {
+                    word.text = tidy(&word.text, false);
+                    !word.text.is_empty()
+                });
+                segment.words = words;
+                (!segment.text.is_empty()).then_some(segment)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::Word;
+
+    fn word(text: &str) -> Word {
+        Word { start: 0.0, end: 0.1, text: text.to_string(), probability: 1.0 }
+    }
+
+    fn segment(text: &str, words: &[&str]) -> Segment {
+        Segment {
+            start: 0.0,
+            end: 1.0,
+            text: text.to_string(),
+            words: words.iter().map(|w| word(w)).collect(),
+            avg_logprob: 0.0,
+            no_speech_prob: 0.0,
+            temperature: 0.0,
+            speaker_turn_next: false,
+        }
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        let options = SuppressOptions::default();
+        let segments = vec![
+            segment(" [музыка]", &["[музыка]"]),
+            segment(" Привет (тихо смеется), как дела? ♪", &["Привет", "(тихо", "смеется),", "как", "дела?", "♪"]),
+        ];
+        let cleaned = options.clean(segments);
+
+        assert_eq!(cleaned.len(), 1);
+        assert_eq!(cleaned[0].text, " Привет как дела?");
+        let words: Vec<&str> = cleaned[0].words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, ["Привет", "как", "дела?"]);
+    }
+
+    #[test]
+    fn test_keeps_punctuation_words() {
+        let options = SuppressOptions::default();
+        let cleaned = options.clean(vec![
+            segment(" Москва — столица", &["Москва", "—", "столица"]),
+            segment(" Итак — (пауза) начнем", &["Итак", "—", "(пауза)", "начнем"]),
+        ]);
+
+        assert_eq!(cleaned[0].text, " Москва — столица");
+        assert_eq!(cleaned[0].words.len(), 3);
+        assert_eq!(cleaned[1].text, " Итак — начнем");
+        let words: Vec<&str> = cleaned[1].words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, ["Итак", "—", "начнем"]);
+    }
+
+    #[test]
+    fn test_unclosed_bracket_stays_in_segment() {
+        let options = SuppressOptions::default();
+        let cleaned = options.clean(vec![
+            segment(" Привет (смеется", &["Привет", "(смеется"]),
+            segment(" как дела?", &["как", "дела?"]),
+        ]);
+
+        let words: Vec<&str> = cleaned[1].words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, ["как", "дела?"]);
+    }
+
+    #[test]
+    fn test_remove_pattern() {
+        assert_eq!(remove_pattern("Редактор субтитров А.Семкин Корректор А.Егорова", "Редактор*Семкин", ""), " Корректор А.Егорова");
+        assert_eq!(remove_pattern("ээ ну ээ да", "ээ ", ""), "ну да");
+        assert_eq!(remove_pattern("<a> и <b>", "<*>", ""), " и ");
+        assert_eq!(remove_pattern("текст", "*", ""), "текст");
+        assert_eq!(remove_pattern("a<b", "<*>", ""), "a<b");
+        assert_eq!(remove_pattern("да. Редактор субтитров А.Семкин", "Редактор субтитров*", ""), "да. ");
+    }
+
+    #[test]
+    fn test_clean_with_patterns_only() {
+        let options = SuppressOptions {
+            strip_annotations: false,
+            patterns: vec!["ээ".to_string()],
+            ..Default::default()
+        };
+        let cleaned = options.clean(vec![segment(" ээ [шум] да", &["ээ", "[шум]", "да"])]);
+        assert_eq!(cleaned[0].text, " [шум] да");
+        assert_eq!(cleaned[0].words.len(), 2);
+    }
+
+    #[test]
+    fn test_multi_word_pattern_removes_words() {
+        let options = SuppressOptions {
+            patterns: vec!["ээ".to_string(), "Редактор субтитров*".to_string()],
+            ..Default::default()
+        };
+        let cleaned = options.clean(vec![
+            segment(" ээ да", &["ээ", "да"]),
+            segment(" Редактор субтитров А.Семкин", &["Редактор", "субтитров", "А.Семкин"]),
+            segment(" Итак, редактор субтитров", &["Итак,", "редактор", "субтитров"]),
+        ]);
+
+        assert_eq!(cleaned.len(), 2);
+        for segment in &cleaned {
+            let words: Vec<&str> = segment.words.iter().map(|w| w.text.as_str()).collect();
+            assert_eq!(segment.text.trim(), words.join(" "));
+        }
+        assert_eq!(cleaned[1].text, " Итак, редактор субтитров", "patterns are case-sensitive");
+    }
+}
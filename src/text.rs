@@ -0,0 +1,22 @@
+//! Text helpers shared by transcript filtering and glossary matching.
+
+/// Lowercase `text` and reduce it to words separated by single spaces
+pub fn normalize_phrase(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phrase() {
+        assert_eq!(normalize_phrase(" Продолжение  следует..."), "продолжение следует");
+        assert_eq!(normalize_phrase("Kubernetes-кластер!"), "kubernetes кластер");
+        assert_eq!(normalize_phrase(" — "), "");
+    }
+}
@@ -0,0 +1,83 @@
+//! Speech-to-text abstraction used by the app.
+//! `WhisperModel` is the real implementation; tests use a scripted fake.
+
+use crate::audio_processor::AudioChunk;
+use crate::whisper::{Transcript, WhisperModel};
+
+/// Turns processed audio chunks into a transcript
+pub trait Transcriber {
+    /// Transcribe chunks of one recording, in order
+    fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> anyhow::Result<Transcript>;
+}
+
+impl Transcriber for WhisperModel {
+    fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> anyhow::Result<Transcript> {
+        WhisperModel::transcribe_chunks(self, chunks)
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::audio_processor::SAMPLE_RATE;
+    use crate::whisper::Segment;
+
+    /// Deterministic transcriber returning scripted replies in order
+    pub struct ScriptedTranscriber {
+        replies: Mutex<VecDeque<Result<String, String>>>,
+    }
+
+    impl ScriptedTranscriber {
+        /// Reply with `texts` one call at a time
+        pub fn new(texts: &[&str]) -> Self {
+            Self {
+                replies: Mutex::new(texts.iter().map(|t| Ok(t.to_string())).collect()),
+            }
+        }
+
+        /// Queue a failing call
+        pub fn then_fail(self, message: &str) -> Self {
+            self.replies.lock().unwrap().push_back(Err(message.to_string()));
+            self
+        }
+
+        /// Replies not consumed yet
+        pub fn remaining(&self) -> usize {
+            self.replies.lock().unwrap().len()
+        }
+    }
+
+    impl Transcriber for ScriptedTranscriber {
+        fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> anyhow::Result<Transcript> {
+            let reply = self
+                .replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| anyhow::anyhow!("No scripted reply left"))?;
+            let text = reply.map_err(|e| anyhow::anyhow!(e))?;
+
+            // One segment spanning all chunks
+            let start = chunks.first().map_or(0.0, |c| c.offset_secs);
+            let end = chunks
+                .last()
+                .map_or(0.0, |c| c.offset_secs + c.samples.len() as f32 / SAMPLE_RATE as f32);
+            Ok(Transcript {
+                segments: vec![Segment {
+                    start,
+                    end,
+                    text,
+                    words: Vec::new(),
+                    avg_logprob: 0.0,
+                    no_speech_prob: 0.0,
+                    temperature: 0.0,
+                    speaker_turn_next: false,
+                }],
+                ..Default::default()
+            })
+        }
+    }
+}
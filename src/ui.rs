@@ -1,27 +1,159 @@
 use crossterm::{
     event::{self, Event, KeyCode},
-    terminal::{enable_raw_mode, disable_raw_mode},
+    style::Stylize,
+    terminal::{self, enable_raw_mode, disable_raw_mode},
 };
 use std::io::{self, Write};
+use std::time::Duration;
 
-pub fn run_ui<F>(mut on_toggle: F) -> anyhow::Result<()>
+use crate::whisper::{Segment, Transcript, Word};
+
+/// Words below this probability are shown dimmed and underlined
+const LOW_CONFIDENCE: f32 = 0.5;
+
+/// Key-triggered UI actions
+pub enum Action {
+    /// SPACE: start or stop recording
+    ToggleRecording,
+    /// T: switch translate-to-English mode
+    ToggleTranslate,
+    /// M: load the next installed model
+    SwitchModel,
+    /// B: switch between greedy decoding and beam search
+    ToggleBeamSearch,
+    /// R: transcribe the last recording again with the current settings
+    Rerun,
+    /// C: forget the session context carried between recordings
+    ResetContext,
+    /// ESC while transcribing: stop the running transcription
+    Cancel,
+    /// No key pressed within the poll interval
+    Tick,
+}
+
+/// How often `Action::Tick` fires while idle
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Format seconds as m:ss.s
+fn format_time(secs: f32) -> String {
+    let minutes = (secs / 60.0).floor();
+    format!("{}:{:04.1}", minutes, secs - minutes * 60.0)
+}
+
+/// Render transcript text, marking words Whisper was unsure about.
+/// With several speaker turns, each starts on its own line.
+pub fn render_transcript(transcript: &Transcript) -> String {
+    let turns = transcript.turns();
+    if turns.len() <= 1 {
+        return render_segments(&transcript.segments);
+    }
+    turns
+        .iter()
+        .map(|turn| format!("\r\n   - {}", render_segments(turn)))
+        .collect()
+}
+
+/// Render the words of consecutive segments
+fn render_segments(segments: &[Segment]) -> String {
+    let words: Vec<&Word> = segments.iter().flat_map(|s| &s.words).collect();
+    if words.is_empty() {
+        return segments.iter().map(|s| s.text.as_str()).collect::<String>().trim().to_string();
+    }
+
+    words
+        .iter()
+        .map(|w| {
+            if w.probability < LOW_CONFIDENCE {
+                w.text.as_str().dim().underlined().to_string()
+            } else {
+                w.text.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// List low-confidence words with their position in the recording
+pub fn render_unsure_words(transcript: &Transcript) -> Option<String> {
+    let unsure: Vec<String> = transcript
+        .segments
+        .iter()
+        .flat_map(|s| &s.words)
+        .filter(|w| w.probability < LOW_CONFIDENCE)
+        .map(|w| format!("{} ({}–{})", w.text, format_time(w.start), format_time(w.end)))
+        .collect();
+
+    if unsure.is_empty() {
+        None
+    } else {
+        Some(unsure.join(", "))
+    }
+}
+
+/// Overwrite the current line with a partial transcript, keeping its tail
+/// when it is wider than the terminal
+pub fn show_partial(text: &str) {
+    let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+    print!("\r\x1b[2K{}", fit_tail(&format!("… {}", text), width.saturating_sub(1)).dim());
+    io::stdout().flush().unwrap();
+}
+
+/// Last `width` characters of `text`
+fn fit_tail(text: &str, width: usize) -> &str {
+    let count = text.chars().count();
+    if count <= width {
+        return text;
+    }
+    let start = text.char_indices().nth(count - width).map_or(text.len(), |(i, _)| i);
+    &text[start..]
+}
+
+/// Run the key loop until ESC (while `is_busy` is false) or Ctrl+C
+pub fn run_ui<B, F>(is_busy: B, mut on_action: F) -> anyhow::Result<()>
 where
-    F: FnMut() -> (),
+    B: Fn() -> bool,
+    F: FnMut(Action),
 {
     enable_raw_mode()?;
-    
+
     // Explicitly using print! + \r\n and flush
     print!("\r\n=== Voice Agent v0.2 (Manual Mode) ===\r\n");
     print!("\r\n[ SPACE ] Start / Stop recording\r\n");
-    print!("[ ESC   ] Quit\r\n\r\n");
+    print!("[ T     ] Translate to English on / off\r\n");
+    print!("[ M     ] Switch to the next installed model\r\n");
+    print!("[ B     ] Greedy / beam search decoding\r\n");
+    print!("[ R     ] Re-run the last recording\r\n");
+    print!("[ C     ] Clear the session context\r\n");
+    print!("[ ESC   ] Cancel transcription / Quit\r\n\r\n");
     io::stdout().flush()?;
 
+    // Ctrl+C while transcriptions are queued asks for a second press
+    let mut quit_armed = false;
     loop {
+        if !event::poll(TICK_INTERVAL)? {
+            on_action(Action::Tick);
+            continue;
+        }
         if let Event::Key(k) = event::read()? {
+            let ctrl_c =
+                k.code == KeyCode::Char('c') && k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+            if ctrl_c && is_busy() && !quit_armed {
+                print!("\r⚠️  Transcriptions are still queued: press Ctrl+C again to quit after them\r\n");
+                io::stdout().flush()?;
+                quit_armed = true;
+                continue;
+            }
+            quit_armed = false;
             match k.code {
-                KeyCode::Char(' ') => on_toggle(),
+                KeyCode::Char(' ') => on_action(Action::ToggleRecording),
+                KeyCode::Char('t') | KeyCode::Char('T') => on_action(Action::ToggleTranslate),
+                KeyCode::Char('m') | KeyCode::Char('M') => on_action(Action::SwitchModel),
+                KeyCode::Char('b') | KeyCode::Char('B') => on_action(Action::ToggleBeamSearch),
+                KeyCode::Char('r') | KeyCode::Char('R') => on_action(Action::Rerun),
+                KeyCode::Esc if is_busy() => on_action(Action::Cancel),
                 KeyCode::Esc => break,
-                KeyCode::Char('c') if k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => break,
+                KeyCode::Char('c') if ctrl_c => break,
+                KeyCode::Char('c') | KeyCode::Char('C') => on_action(Action::ResetContext),
                 _ => {}
             }
         }
@@ -31,3 +163,15 @@ where
     println!("\nGoodbye.");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_tail() {
+        assert_eq!(fit_tail("привет", 10), "привет");
+        assert_eq!(fit_tail("привет мир", 3), "мир");
+        assert_eq!(fit_tail("abc", 0), "");
+    }
+}
@@ -1,28 +1,680 @@
-use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy};
-use std::ffi::c_void;
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState, WhisperSysContext,
+    WhisperSysState, WhisperToken, WhisperTokenData,
+};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_int, c_void};
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::audio_processor::{AudioChunk, SAMPLE_RATE};
+use crate::cancel::{Cancel, CancelFlag, CancelReason};
+use crate::codeswitch::{CodeSwitchOptions, Transliterations};
+use crate::glossary::Glossary;
+use crate::models;
+use crate::prompt::PromptOptions;
+use crate::suppress::SuppressOptions;
+use crate::text::normalize_phrase;
+
+/// Language setting that enables whisper's language detection
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Language used when nothing else is configured
+pub const DEFAULT_LANGUAGE: &str = "ru";
+
+/// Encoder frames covering the full 30 s window
+const FULL_AUDIO_CTX: i32 = 1500;
+
+/// Shortest segment whose language is detected for code-switching
+const MIN_REDECODE_SECS: f32 = 1.0;
+
+/// Most decoders whisper.cpp runs at once (`WHISPER_MAX_DECODERS`), which
+/// bounds both the beam size and best-of
+const MAX_DECODERS: i32 = 8;
+
+/// Smallest fallback temperature step; smaller ones mean dozens of
+/// re-decodes of one chunk
+const MIN_TEMPERATURE_INC: f32 = 0.1;
+
+/// CPU settings for inference
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceOptions {
+    /// Threads used by whisper.cpp (default: number of physical cores)
+    pub threads: Option<usize>,
+    /// Encoder context in frames, 1500 = 30 s; 0 uses the full window.
+    /// Smaller values are faster but ignore audio beyond the context.
+    pub audio_ctx: i32,
+    /// Size the encoder context to each chunk's length instead
+    pub fit_audio_ctx: bool,
+    /// Use flash attention in the model
+    pub flash_attn: bool,
+    /// Chunks transcribed concurrently (default: 1). Above 1, chunks are
+    /// prompted without the previous chunk's text.
+    pub parallel_chunks: usize,
+    /// Stop a transcription running longer than this, keeping the text
+    /// decoded so far (default: no limit)
+    pub timeout_secs: Option<f32>,
+}
+
+impl InferenceOptions {
+    /// Thread count to use
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(num_cpus::get_physical).max(1)
+    }
+
+    /// Encoder context for a chunk of `samples` samples
+    fn audio_ctx_for(&self, samples: usize) -> i32 {
+        if !self.fit_audio_ctx {
+            return self.audio_ctx;
+        }
+        // 50 frames per second, plus a little margin at the end
+        let frames = (samples * 50).div_ceil(SAMPLE_RATE) as i32 + 64;
+        frames.min(FULL_AUDIO_CTX)
+    }
+
+    /// Transcription time limit, if set
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.filter(|&t| t > 0.0).map(Duration::from_secs_f32)
+    }
+}
+
+/// Decoding strategy
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeStrategy {
+    Greedy,
+    BeamSearch,
+}
+
+/// Decoding settings passed to whisper.cpp
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodeOptions {
+    /// Greedy sampling or beam search (default: greedy)
+    pub strategy: DecodeStrategy,
+    /// Beam width for beam search, at most 8 (default: 5)
+    pub beam_size: i32,
+    /// Candidates sampled when greedy decoding runs above temperature 0, at
+    /// most 8 (default: 1)
+    pub best_of: i32,
+    /// Initial sampling temperature (default: 0.0)
+    pub temperature: f32,
+    /// Temperature step for fallback re-decoding, at least 0.1; 0 disables
+    /// it (default: 0.2)
+    pub temperature_inc: f32,
+    /// Text compressing better than this loops: a chunk is re-decoded, a
+    /// segment left looping is dropped by the filter (default: 2.4)
+    pub compression_ratio_thold: f32,
+    /// No-speech probability threshold (default: 0.6)
+    pub no_speech_thold: f32,
+    /// Average log-probability below which a decode falls back (default: -1.0)
+    pub logprob_thold: f32,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            strategy: DecodeStrategy::Greedy,
+            beam_size: 5,
+            best_of: 1,
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            compression_ratio_thold: 2.4,
+            no_speech_thold: 0.6,
+            logprob_thold: -1.0,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Check that the values are usable by whisper.cpp
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=MAX_DECODERS).contains(&self.beam_size) {
+            anyhow::bail!("beam_size must be between 1 and {}", MAX_DECODERS);
+        }
+        if !(1..=MAX_DECODERS).contains(&self.best_of) {
+            anyhow::bail!("best_of must be between 1 and {}", MAX_DECODERS);
+        }
+        if !(0.0..=1.0).contains(&self.temperature) {
+            anyhow::bail!("temperature must be between 0.0 and 1.0");
+        }
+        if self.temperature_inc != 0.0 && self.temperature_inc < MIN_TEMPERATURE_INC {
+            anyhow::bail!("temperature_inc must be 0 (no fallback) or at least {}", MIN_TEMPERATURE_INC);
+        }
+        Ok(())
+    }
+
+    /// Whether a chunk decoded as `segments` should be re-decoded at a higher
+    /// temperature: its text loops, or Whisper was unsure of it. Unsure text
+    /// on a likely silent chunk is left to the hallucination filter.
+    fn needs_fallback(&self, segments: &[Segment], no_speech_prob: f32) -> bool {
+        if segments.is_empty() {
+            return false;
+        }
+        let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+        if compression_ratio(&text) > self.compression_ratio_thold {
+            return true;
+        }
+        let avg_logprob = segments.iter().map(|s| s.avg_logprob).sum::<f32>() / segments.len() as f32;
+        avg_logprob < self.logprob_thold && no_speech_prob <= self.no_speech_thold
+    }
+
+    fn sampling_strategy(&self) -> SamplingStrategy {
+        match self.strategy {
+            DecodeStrategy::Greedy => SamplingStrategy::Greedy { best_of: self.best_of },
+            DecodeStrategy::BeamSearch => SamplingStrategy::BeamSearch {
+                beam_size: self.beam_size,
+                patience: -1.0,
+            },
+        }
+    }
+}
+
+/// Settings for dropping segments Whisper made up
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterOptions {
+    /// Drop hallucinated segments at all; off also skips the no-speech
+    /// probe, an extra encoder pass per chunk (default: true)
+    pub enabled: bool,
+    /// Phrases Whisper invents on silence, matched case-insensitively as
+    /// whole words at the start or end of a segment
+    pub blocklist: Vec<String>,
+}
+
+impl Default for FilterOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            blocklist: [
+                "Продолжение следует",
+                "Субтитры сделал",
+                "Субтитры создавал",
+                "Редактор субтитров",
+                "Спасибо за просмотр",
+                "Подписывайтесь на канал",
+                "Thank you for watching",
+                "Thanks for watching",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// Why a segment was dropped as a hallucination
+#[derive(Debug, PartialEq)]
+enum Hallucination {
+    /// Whisper itself rated the chunk as silence
+    NoSpeech,
+    /// The text repeats itself
+    Repetition,
+    /// The text starts or ends with a blocklisted phrase
+    Blocklisted,
+}
+
+/// Ratio of the UTF-8 size of `text` to its zlib-compressed size, as used
+/// by OpenAI Whisper to spot repetition loops
+fn compression_ratio(text: &str) -> f32 {
+    if text.is_empty() {
+        return 0.0;
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder
+        .write_all(text.as_bytes())
+        .and_then(|_| encoder.finish())
+        .map_or(text.len(), |c| c.len());
+    text.len() as f32 / compressed as f32
+}
+
+impl FilterOptions {
+    /// Check a segment against the filters; `previous` is the text kept
+    /// before it, for catching a sentence repeated across segments
+    fn check(&self, segment: &Segment, previous: Option<&str>, decode: &DecodeOptions) -> Option<Hallucination> {
+        // OpenAI Whisper's rule: a likely no-speech chunk is only skipped
+        // when the decode was unsure too
+        if segment.no_speech_prob > decode.no_speech_thold && segment.avg_logprob < decode.logprob_thold {
+            return Some(Hallucination::NoSpeech);
+        }
+
+        let text = normalize_phrase(&segment.text);
+        if compression_ratio(&segment.text) > decode.compression_ratio_thold
+            || (!text.is_empty() && previous.is_some_and(|p| normalize_phrase(p) == text))
+        {
+            return Some(Hallucination::Repetition);
+        }
+
+        // Phantom phrases open or close a segment ("Субтитры сделал ..."); in
+        // the middle of speech they are more likely said
+        let padded = format!(" {} ", text);
+        let blocked = self
+            .blocklist
+            .iter()
+            .map(|phrase| format!(" {} ", normalize_phrase(phrase)))
+            .any(|phrase| !phrase.trim().is_empty() && (padded.starts_with(&phrase) || padded.ends_with(&phrase)));
+        blocked.then_some(Hallucination::Blocklisted)
+    }
+
+    /// Drop hallucinated segments of one chunk
+    fn apply(&self, segments: Vec<Segment>, decode: &DecodeOptions) -> Vec<Segment> {
+        if !self.enabled {
+            return segments;
+        }
+        let mut kept: Vec<Segment> = Vec::with_capacity(segments.len());
+        for segment in segments {
+            let previous = kept.last().map(|s| s.text.as_str());
+            if self.check(&segment, previous, decode).is_none() {
+                kept.push(segment);
+            }
+        }
+        kept
+    }
+}
+
+/// Logit bias towards glossary terms
+struct GlossaryBias {
+    /// Token sequence of each term, as it appears after a space
+    sequences: Vec<Vec<WhisperToken>>,
+    /// Added to the logit of the next token of any term
+    boost: f32,
+    /// Text tokens are the ids below end-of-transcript
+    eot: WhisperToken,
+}
+
+impl GlossaryBias {
+    /// Boost the token that starts a term, or continues one whose first
+    /// tokens were just decoded
+    fn apply(&self, history: &[WhisperToken], logits: &mut [f32]) {
+        let text: Vec<WhisperToken> = history.iter().copied().filter(|&t| t < self.eot).collect();
+        let mut next: Vec<WhisperToken> = self
+            .sequences
+            .iter()
+            .filter_map(|seq| {
+                // Longest decoded prefix of the term, the empty one always matches
+                (0..seq.len()).rev().find(|&k| text.ends_with(&seq[..k])).map(|k| seq[k])
+            })
+            .collect();
+        next.sort_unstable();
+        next.dedup();
+
+        for token in next {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit += self.boost;
+            }
+        }
+    }
+}
+
+/// Logit changes made while decoding, shared with the logits filter callback
+struct LogitsFilter {
+    /// Boost towards glossary terms, when boosting is on
+    glossary: Option<GlossaryBias>,
+    /// Tokens never decoded
+    suppressed: Vec<WhisperToken>,
+    /// Length of the logits array
+    n_vocab: usize,
+}
+
+impl LogitsFilter {
+    fn apply(&self, history: &[WhisperToken], logits: &mut [f32]) {
+        if let Some(bias) = &self.glossary {
+            bias.apply(history, logits);
+        }
+        for &token in &self.suppressed {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
+    }
+}
+
+/// whisper.cpp logits filter: applies the `LogitsFilter` passed as user data
+unsafe extern "C" fn logits_filter_callback(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    tokens: *const WhisperTokenData,
+    n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    if logits.is_null() || user_data.is_null() {
+        return;
+    }
+    let filter = &*(user_data as *const LogitsFilter);
+    let history: Vec<WhisperToken> = if tokens.is_null() || n_tokens <= 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(tokens, n_tokens as usize).iter().map(|t| t.id).collect()
+    };
+    filter.apply(&history, std::slice::from_raw_parts_mut(logits, filter.n_vocab));
+}
+
+/// whisper.cpp abort callback: checks the `Cancel` passed as user data
+unsafe extern "C" fn abort_callback(user_data: *mut c_void) -> bool {
+    !user_data.is_null() && (*(user_data as *const Cancel)).is_cancelled()
+}
+
+/// whisper.cpp encoder-begin callback that stops `full` before encoding
+unsafe extern "C" fn stop_before_encoder(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    _user_data: *mut c_void,
+) -> bool {
+    false
+}
+
+/// Language detected in `auto` mode
+pub struct DetectedLanguage {
+    /// Whisper language code, e.g. "ru"
+    pub code: &'static str,
+    /// Detection probability in [0, 1]
+    pub probability: f32,
+}
+
+/// A word assembled from one or more tokens
+pub struct Word {
+    /// Start time in seconds from the beginning of the recording
+    pub start: f32,
+    /// End time in seconds from the beginning of the recording
+    pub end: f32,
+    /// Word text, including attached punctuation
+    pub text: String,
+    /// Mean probability of the word's tokens
+    pub probability: f32,
+}
+
+/// A text token as decoded by Whisper
+struct Token {
+    /// Raw token bytes; a Cyrillic character may span two tokens
+    bytes: Vec<u8>,
+    start: f32,
+    end: f32,
+    probability: f32,
+    logprob: f32,
+}
+
+/// Group tokens into words. A token starting with a space opens a new word;
+/// bytes are joined before decoding so split UTF-8 characters survive.
+fn group_words(tokens: &[Token]) -> Vec<Word> {
+    let mut groups: Vec<Vec<&Token>> = Vec::new();
+    for token in tokens {
+        match groups.last_mut() {
+            Some(group) if !token.bytes.starts_with(b" ") => group.push(token),
+            _ => groups.push(vec![token]),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|group| {
+            let bytes: Vec<u8> = group.iter().flat_map(|t| t.bytes.iter().copied()).collect();
+            let text = String::from_utf8_lossy(&bytes).trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+            Some(Word {
+                start: group[0].start,
+                end: group[group.len() - 1].end,
+                text,
+                probability: group.iter().map(|t| t.probability).sum::<f32>() / group.len() as f32,
+            })
+        })
+        .collect()
+}
+
+/// A decoded piece of text with its position in the recording
+pub struct Segment {
+    /// Start time in seconds from the beginning of the recording
+    pub start: f32,
+    /// End time in seconds from the beginning of the recording
+    pub end: f32,
+    pub text: String,
+    /// Words with their own timestamps and confidence
+    pub words: Vec<Word>,
+    /// Average log-probability of the segment's text tokens
+    pub avg_logprob: f32,
+    /// Probability that the chunk holding this segment contains no speech
+    pub no_speech_prob: f32,
+    /// Sampling temperature the chunk was finally decoded at
+    pub temperature: f32,
+    /// A different speaker talks after this segment (diarization mode)
+    pub speaker_turn_next: bool,
+}
+
+/// Result of a transcription
+#[derive(Default)]
+pub struct Transcript {
+    /// Decoded segments in recording order; translated in translate mode
+    pub segments: Vec<Segment>,
+    /// Detected language, only set when the language is `auto`
+    pub language: Option<DetectedLanguage>,
+    /// Untranslated transcript, when translating with the original kept
+    pub original: Option<String>,
+    /// Set when the transcription was stopped early; the segments are
+    /// those decoded until then
+    pub cancelled: Option<CancelReason>,
+}
+
+impl Transcript {
+    /// Plain text of all segments
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Segments grouped into speaker turns; a single turn unless diarizing
+    pub fn turns(&self) -> Vec<&[Segment]> {
+        self.segments
+            .split_inclusive(|s| s.speaker_turn_next)
+            .collect()
+    }
+
+    /// Highest temperature any chunk needed, 0.0 for an empty transcript
+    pub fn max_temperature(&self) -> f32 {
+        self.segments.iter().map(|s| s.temperature).fold(0.0, f32::max)
+    }
+
+    /// Append the segments of the next chunk. Segments that fall into the
+    /// overlap with the previous chunk, already covered, are dropped.
+    fn push_segments(&mut self, segments: Vec<Segment>) {
+        let covered_until = self.segments.last().map_or(f32::NEG_INFINITY, |s| s.end);
+        self.segments.extend(
+            segments
+                .into_iter()
+                .filter(|s| (s.start + s.end) / 2.0 >= covered_until),
+        );
+    }
+}
+
+/// Speech checks run on a chunk before decoding; the default stands for
+/// a chunk that was not probed
+#[derive(Default)]
+struct ChunkProbe {
+    /// Detected language, only when detection was requested
+    language: Option<DetectedLanguage>,
+    /// Probability of each language id, empty without detection
+    language_probs: Vec<f32>,
+    /// Probability of the no-speech token right after start-of-transcript
+    no_speech_prob: f32,
+}
+
+/// Settings shared by every chunk of one transcription pass
+struct Pass<'a> {
+    translate: bool,
+    options: &'a DecodeOptions,
+    /// whisper.cpp threads per chunk
+    threads: usize,
+    /// Stops whisper.cpp and the remaining chunks
+    cancel: Cancel,
+}
+
+/// A state borrowed from the model's pool, returned to it when dropped
+struct PooledState<'a> {
+    state: Option<WhisperState>,
+    pool: &'a Mutex<Vec<WhisperState>>,
+}
+
+impl Deref for PooledState<'_> {
+    type Target = WhisperState;
+
+    fn deref(&self) -> &WhisperState {
+        self.state.as_ref().expect("state taken")
+    }
+}
+
+impl DerefMut for PooledState<'_> {
+    fn deref_mut(&mut self) -> &mut WhisperState {
+        self.state.as_mut().expect("state taken")
+    }
+}
+
+impl Drop for PooledState<'_> {
+    fn drop(&mut self) {
+        if let (Some(state), Ok(mut pool)) = (self.state.take(), self.pool.lock()) {
+            pool.push(state);
+        }
+    }
+}
 
 pub struct WhisperModel {
     ctx: WhisperContext,
+    /// File the model was loaded from
+    model_path: PathBuf,
+    /// Idle decoder states, reused so KV caches are not re-allocated per chunk
+    states: Mutex<Vec<WhisperState>>,
     /// Calibration prompt for improved accuracy (set from voice profile)
     calibration_prompt: Option<String>,
+    /// Text of the session's recent recordings, prompting the next one
+    session_context: String,
+    /// Whisper language code, or "auto" to detect it
+    language: String,
+    /// Translate speech to English instead of transcribing it
+    translate: bool,
+    /// Language and translate mode chosen for the session; `language` and
+    /// `translate` follow them whenever the model is multilingual
+    session_language: String,
+    session_translate: bool,
+    /// In translate mode, also produce the untranslated transcript
+    keep_original: bool,
+    /// Default decoding settings
+    decode: DecodeOptions,
+    /// Hallucination filter applied to every chunk
+    filter: FilterOptions,
+    /// Initial prompt token budget
+    prompt: PromptOptions,
+    /// Glossary whose terms are put into the prompt
+    glossary: Option<Glossary>,
+    /// Logit bias for glossary tokens, 0 disables boosting
+    glossary_boost: f32,
+    /// Token suppression and text clean-up
+    suppress: SuppressOptions,
+    /// Glossary boost and suppressed tokens, when there are any
+    logits_filter: Option<LogitsFilter>,
+    /// Mark speaker turns (needs a tinydiarize model, e.g. small.en-tdrz)
+    diarize: bool,
+    /// Per-chunk language detection for mixed-language speech
+    code_switch: CodeSwitchOptions,
+    /// Transliterated terms respelled in Latin
+    transliterations: Option<Transliterations>,
+    /// Thread and encoder settings
+    inference: InferenceOptions,
+    /// Raised to cancel the running transcription
+    cancel_flag: CancelFlag,
 }
 
 impl WhisperModel {
-    pub fn new(model_path: &str) -> anyhow::Result<Self> {
-        // Suppress logs
-        unsafe {
-            whisper_rs::set_log_callback(Some(null_log_callback), std::ptr::null_mut());
-        }
-
-        let ctx = WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
-            .map_err(|e| anyhow::anyhow!("Failed to load model: {}", e))?;
-        
-        Ok(Self { 
-            ctx,
+    pub fn new(model_path: &Path, inference: InferenceOptions) -> anyhow::Result<Self> {
+        Ok(Self {
+            ctx: Self::load_context(model_path, &inference)?,
+            model_path: model_path.to_path_buf(),
             calibration_prompt: None,
+            session_context: String::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            translate: false,
+            session_language: DEFAULT_LANGUAGE.to_string(),
+            session_translate: false,
+            keep_original: false,
+            decode: DecodeOptions::default(),
+            filter: FilterOptions::default(),
+            prompt: PromptOptions::default(),
+            glossary: None,
+            glossary_boost: 0.0,
+            suppress: SuppressOptions::default(),
+            logits_filter: None,
+            diarize: false,
+            code_switch: CodeSwitchOptions::default(),
+            transliterations: None,
+            inference,
+            cancel_flag: CancelFlag::default(),
+            states: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Load a model file, checking its header first for a clear error
+    fn load_context(model_path: &Path, inference: &InferenceOptions) -> anyhow::Result<WhisperContext> {
+        let header = models::inspect(model_path)?;
+        let path = model_path.to_str()
+            .ok_or_else(|| anyhow::anyhow!("Model path is not valid UTF-8: {}", model_path.display()))?;
+        let mut ctx_params = WhisperContextParameters::default();
+        ctx_params.flash_attn(inference.flash_attn);
+        log::info!(
+            "Loading model {} ({} {}, {})",
+            model_path.display(),
+            header.model_type(),
+            header.ftype_name(),
+            if header.is_multilingual() { "multilingual" } else { "English-only" }
+        );
+        WhisperContext::new_with_params(path, ctx_params).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load model {} ({} {}, possibly corrupted): {}",
+                model_path.display(),
+                header.model_type(),
+                header.ftype_name(),
+                e
+            )
         })
     }
 
+    /// Replace the loaded model, keeping every other setting. Translation
+    /// is turned off and the language set to English while the model is
+    /// English-only; the session's choice comes back with a multilingual
+    /// one. On error the current model stays loaded.
+    pub fn switch_model(&mut self, model_path: &Path) -> anyhow::Result<()> {
+        let ctx = Self::load_context(model_path, &self.inference)?;
+        // Token ids differ between vocabularies
+        let logits_filter = self.build_logits_filter(&ctx)?;
+
+        self.clear_state_pool();
+        self.ctx = ctx;
+        self.model_path = model_path.to_path_buf();
+        self.logits_filter = logits_filter;
+        let (language, translate) =
+            model_language(&self.session_language, self.session_translate, self.ctx.is_multilingual());
+        if language != self.language {
+            log::info!("Language {} -> {} for model {}", self.language, language, model_path.display());
+        }
+        self.language = language.to_string();
+        self.translate = translate;
+        Ok(())
+    }
+
+    /// File the current model was loaded from
+    pub fn model_path(&self) -> &Path {
+        &self.model_path
+    }
+
+    /// Flag that cancels the running transcription from another thread
+    pub fn cancel_flag(&self) -> CancelFlag {
+        self.cancel_flag.clone()
+    }
+
     /// Set the calibration prompt from voice profile
     pub fn set_calibration_prompt(&mut self, prompt: &str) {
         if !prompt.is_empty() {
@@ -30,91 +682,875 @@ impl WhisperModel {
         }
     }
 
-    pub fn transcribe(&self, audio: &[f32]) -> anyhow::Result<String> {
-        let mut state = self.ctx.create_state()
-            .map_err(|e| anyhow::anyhow!("Failed to create state: {}", e))?;
+    /// Set the text of recent recordings that prompts the first chunk of
+    /// the next one, in place of a previous chunk's text. Empty clears it.
+    pub fn set_session_context(&mut self, text: &str) {
+        self.session_context = text.trim().to_string();
+    }
 
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    /// Set the transcription language ("ru", "en", "uk", ... or "auto")
+    pub fn set_language(&mut self, language: &str) -> anyhow::Result<()> {
+        let language = language.trim().to_lowercase();
+        if language != AUTO_LANGUAGE && whisper_rs::get_lang_id(&language).is_none() {
+            anyhow::bail!("Unknown language: {}", language);
+        }
+        self.language = model_language(&language, false, self.ctx.is_multilingual()).0.to_string();
+        self.session_language = language;
+        Ok(())
+    }
+
+    /// Current language setting
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Enable or disable translation to English
+    pub fn set_translate(&mut self, translate: bool) -> anyhow::Result<()> {
+        if translate && !self.ctx.is_multilingual() {
+            anyhow::bail!("Translation requires a multilingual model (not *.en)");
+        }
+        self.translate = translate;
+        self.session_translate = translate;
+        Ok(())
+    }
+
+    /// Whether translate mode is on
+    pub fn translate(&self) -> bool {
+        self.translate
+    }
+
+    /// Keep the untranslated transcript alongside the translation
+    pub fn set_keep_original(&mut self, keep_original: bool) {
+        self.keep_original = keep_original;
+    }
+
+    /// Enable or disable speaker turn detection. Only tinydiarize models
+    /// (`*-tdrz.bin`) emit turns; others decode as usual.
+    pub fn set_diarize(&mut self, diarize: bool) {
+        self.diarize = diarize;
+    }
 
+    /// Set code-switching and the transliteration dictionary; `None`
+    /// disables the dictionary. Returns whether code-switching is active:
+    /// it needs a session language, so it stays off in auto mode.
+    pub fn set_code_switch(&mut self, options: CodeSwitchOptions, dictionary: Option<Transliterations>) -> anyhow::Result<bool> {
+        if whisper_rs::get_lang_id(&options.second_language).is_none() {
+            anyhow::bail!("Unknown language: {}", options.second_language);
+        }
+        let active = options.enabled && self.language != AUTO_LANGUAGE;
+        if options.enabled && !active {
+            log::warn!("Code-switching is off: it needs a session language, not auto");
+        }
+        self.code_switch = options;
+        self.transliterations = dictionary.filter(|d| !d.is_empty());
+        Ok(active)
+    }
+
+    /// Current default decoding settings
+    pub fn decode_options(&self) -> &DecodeOptions {
+        &self.decode
+    }
+
+    /// Set the default decoding settings
+    pub fn set_decode_options(&mut self, options: DecodeOptions) -> anyhow::Result<()> {
+        options.validate()?;
+        self.decode = options;
+        Ok(())
+    }
+
+    /// Set the hallucination filter
+    pub fn set_filter_options(&mut self, filter: FilterOptions) {
+        self.filter = filter;
+    }
+
+    /// Set the initial prompt token budget
+    pub fn set_prompt_options(&mut self, prompt: PromptOptions) {
+        self.prompt = prompt;
+    }
+
+    /// Use a glossary: its terms go into the prompt and, with a positive
+    /// `boost`, get that logit bias while decoding. `None` removes it.
+    pub fn set_glossary(&mut self, glossary: Option<&Glossary>, boost: f32) -> anyhow::Result<()> {
+        self.glossary = glossary.filter(|g| !g.is_empty()).cloned();
+        self.glossary_boost = boost;
+        self.logits_filter = self.build_logits_filter(&self.ctx)?;
+        Ok(())
+    }
+
+    /// Set token suppression and transcript clean-up
+    pub fn set_suppress_options(&mut self, suppress: SuppressOptions) -> anyhow::Result<()> {
+        self.suppress = suppress;
+        self.logits_filter = self.build_logits_filter(&self.ctx)?;
+        Ok(())
+    }
+
+    /// Tokenize the glossary terms for boosting, if enabled
+    fn build_glossary_bias(&self, ctx: &WhisperContext) -> anyhow::Result<Option<GlossaryBias>> {
+        let Some(glossary) = self.glossary.as_ref().filter(|_| self.glossary_boost > 0.0) else {
+            return Ok(None);
+        };
+        let sequences = glossary
+            .terms()
+            .iter()
+            .map(|term| {
+                let text = format!(" {}", term);
+                ctx.tokenize(&text, text.len() + 1)
+                    .map_err(|e| anyhow::anyhow!("Failed to tokenize glossary term {}: {}", term, e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Some(GlossaryBias {
+            sequences,
+            boost: self.glossary_boost,
+            eot: ctx.token_eot(),
+        }))
+    }
+
+    /// Token ids of the suppressed words, with and without a leading space.
+    /// Words spanning several tokens cannot be banned without also banning
+    /// other words starting the same way, so they are skipped.
+    fn suppressed_tokens(&self, ctx: &WhisperContext) -> anyhow::Result<Vec<WhisperToken>> {
+        let mut tokens = Vec::new();
+        for word in &self.suppress.tokens {
+            let mut single = false;
+            for text in [word.clone(), format!(" {}", word)] {
+                let ids = ctx.tokenize(&text, text.len() + 1)
+                    .map_err(|e| anyhow::anyhow!("Failed to tokenize suppressed token {}: {}", word, e))?;
+                if let [id] = ids[..] {
+                    tokens.push(id);
+                    single = true;
+                }
+            }
+            if !single {
+                log::warn!("Not suppressing {:?}: it is not a single token of this model", word);
+            }
+        }
+        tokens.sort_unstable();
+        tokens.dedup();
+        Ok(tokens)
+    }
+
+    /// Combine glossary boost and token suppression for the logits filter
+    /// of the model loaded as `ctx`
+    fn build_logits_filter(&self, ctx: &WhisperContext) -> anyhow::Result<Option<LogitsFilter>> {
+        let glossary = self.build_glossary_bias(ctx)?;
+        let suppressed = self.suppressed_tokens(ctx)?;
+        if glossary.is_none() && suppressed.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(LogitsFilter {
+            glossary,
+            suppressed,
+            n_vocab: ctx.n_vocab().max(0) as usize,
+        }))
+    }
+
+    /// Number of tokens `text` encodes to
+    fn count_tokens(&self, text: &str) -> usize {
+        // A token covers at least one byte, so this bound always suffices
+        self.ctx.tokenize(text, text.len() + 1).map_or(text.len(), |tokens| tokens.len())
+    }
+
+    /// Take an idle state from the pool, creating one if none is left
+    fn acquire_state(&self) -> anyhow::Result<PooledState<'_>> {
+        let pooled = self.states.lock()
+            .map_err(|_| anyhow::anyhow!("State pool poisoned"))?
+            .pop();
+        let state = match pooled {
+            Some(state) => state,
+            None => self.ctx.create_state()
+                .map_err(|e| anyhow::anyhow!("Failed to create state: {}", e))?,
+        };
+        Ok(PooledState { state: Some(state), pool: &self.states })
+    }
+
+    /// Free all idle states; the next transcription allocates fresh ones
+    pub fn clear_state_pool(&self) {
+        if let Ok(mut pool) = self.states.lock() {
+            pool.clear();
+        }
+    }
+
+    /// Build decoding parameters for the given language. Temperature fallback
+    /// is done per chunk by `decode_chunk`, so whisper.cpp's own is disabled.
+    fn full_params<'a>(&self, language: &'a str, pass: &Pass, temperature: f32) -> FullParams<'a, '_> {
+        let options = pass.options;
+        let mut params = FullParams::new(options.sampling_strategy());
         params.set_print_progress(false);
         params.set_print_special(false);
-        params.set_language(Some("ru"));
-
-        // Apply calibration prompt if set
-        if let Some(ref prompt) = self.calibration_prompt {
-            params.set_initial_prompt(prompt);
+        params.set_language(Some(language));
+        params.set_translate(pass.translate);
+        params.set_token_timestamps(true);
+        params.set_n_threads(pass.threads as i32);
+        params.set_temperature(temperature);
+        params.set_temperature_inc(0.0);
+        params.set_no_speech_thold(options.no_speech_thold);
+        params.set_logprob_thold(options.logprob_thold);
+        params.set_tdrz_enable(self.diarize);
+        // SAFETY: the callback only reads `pass.cancel`, which outlives every
+        // `full` call made with the returned params
+        unsafe {
+            params.set_abort_callback(Some(abort_callback));
+            params.set_abort_callback_user_data(&pass.cancel as *const Cancel as *mut c_void);
         }
+        params.set_suppress_blank(self.suppress.suppress_blank);
+        params.set_suppress_non_speech_tokens(self.suppress.non_speech_tokens);
+        if let Some(filter) = &self.logits_filter {
+            // SAFETY: the callback only reads `filter`, which outlives the
+            // returned params as both borrow `self`
+            unsafe {
+                params.set_filter_logits_callback(Some(logits_filter_callback));
+                params.set_filter_logits_callback_user_data(filter as *const LogitsFilter as *mut c_void);
+            }
+        }
+        params
+    }
+
+    /// Calibration prompt for a pass. The prompt holds Russian text, which
+    /// pulls a translation back into Russian, so it is skipped when translating.
+    fn calibration_prompt(&self, translate: bool) -> Option<&String> {
+        self.calibration_prompt.as_ref().filter(|_| !translate)
+    }
 
+    /// Build prompt: glossary + calibration + end of the previous chunk's
+    /// text, or of the session text for a first chunk, within the token budget
+    /// and whisper's limit of half the text context. Session text is
+    /// untranslated, so it is skipped when translating.
+    fn context_prompt(&self, translate: bool, previous: &str) -> String {
+        let limit = (self.ctx.n_text_ctx() / 2).max(0) as usize;
+        let options = PromptOptions {
+            max_tokens: self.prompt.max_tokens.min(limit),
+            ..self.prompt.clone()
+        };
+        let glossary = self.glossary.as_ref().map(Glossary::prompt);
+        let fixed: Vec<&str> = [glossary.as_ref(), self.calibration_prompt(translate)]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        let previous = match previous.trim() {
+            "" if !translate => self.session_context.as_str(),
+            previous => previous,
+        };
+        options.build(&fixed, previous, |text| self.count_tokens(text))
+    }
+
+    /// Compute the mel of `audio` and set the state's encoder context to
+    /// the one `full` will use. whisper-rs has no setter for the latter, so
+    /// `full` is run with a pinned language and stopped before encoding;
+    /// the error whisper.cpp logs for the stop is downgraded by `logging`.
+    fn prepare_encoder(&self, state: &mut WhisperState, audio: &[f32], threads: usize) -> anyhow::Result<()> {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_print_progress(false);
+        params.set_language(Some(DEFAULT_LANGUAGE));
+        params.set_n_threads(threads as i32);
+        params.set_audio_ctx(self.inference.audio_ctx_for(audio.len()));
+        // SAFETY: the callback touches neither the context nor the state
+        unsafe {
+            params.set_start_encoder_callback(Some(stop_before_encoder));
+        }
         state.full(params, audio)
-            .map_err(|e| anyhow::anyhow!("Failed to run model: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to compute mel: {}", e))?;
+        Ok(())
+    }
+
+    /// Encode `audio` once to detect its language (when asked) and estimate
+    /// how likely it is to contain no speech, with the encoder context used
+    /// for decoding.
+    fn probe(&self, state: &mut WhisperState, audio: &[f32], detect_language: bool, threads: usize) -> anyhow::Result<ChunkProbe> {
+        self.prepare_encoder(state, audio, threads)?;
+
+        // Language detection runs the encoder itself
+        let (language, language_probs) = if detect_language {
+            let (id, probs) = state.lang_detect(0, threads)
+                .map_err(|e| anyhow::anyhow!("Failed to detect language: {}", e))?;
+            let code = whisper_rs::get_lang_str(id)
+                .ok_or_else(|| anyhow::anyhow!("Unknown language id: {}", id))?;
+            let detected = DetectedLanguage {
+                code,
+                probability: probs.get(id as usize).copied().unwrap_or(0.0),
+            };
+            (Some(detected), probs)
+        } else {
+            state.encode(0, threads)
+                .map_err(|e| anyhow::anyhow!("Failed to encode audio: {}", e))?;
+            (None, Vec::new())
+        };
 
-        let mut text = String::new();
+        // No-speech probability as in OpenAI Whisper: the softmax of the
+        // logits after start-of-transcript, taken at the no-speech token
+        state.decode(&[self.ctx.token_sot()], 0, threads)
+            .map_err(|e| anyhow::anyhow!("Failed to decode: {}", e))?;
+        let logits = state.get_logits()
+            .map_err(|e| anyhow::anyhow!("Failed to read logits: {}", e))?;
+        let no_speech_prob = softmax_at(logits, self.ctx.token_nosp() as usize);
+
+        Ok(ChunkProbe { language, language_probs, no_speech_prob })
+    }
+
+    /// Read the decoded segments of one chunk, shifting timestamps by the
+    /// chunk's offset in the recording
+    fn collect_segments(&self, state: &mut WhisperState, offset_secs: f32, no_speech_prob: f32, temperature: f32) -> Vec<Segment> {
+        let eot = self.ctx.token_eot();
         let num_segments = state.full_n_segments().unwrap_or(0);
+        let mut segments = Vec::new();
+
         for i in 0..num_segments {
-            if let Ok(segment) = state.full_get_segment_text(i) {
-                text.push_str(&segment);
-            }
+            let Ok(text) = state.full_get_segment_text(i) else {
+                continue;
+            };
+            // Timestamps are in 10 ms units
+            let t0 = state.full_get_segment_t0(i).unwrap_or(0);
+            let t1 = state.full_get_segment_t1(i).unwrap_or(t0);
+
+            // Text tokens only; special and timestamp tokens follow EOT
+            let tokens: Vec<Token> = (0..state.full_n_tokens(i).unwrap_or(0))
+                .filter_map(|t| state.full_get_token_data(i, t).ok())
+                .filter(|data| data.id < eot)
+                .map(|data| Token {
+                    bytes: self.ctx.token_to_cstr(data.id)
+                        .map(|s| s.to_bytes().to_vec())
+                        .unwrap_or_default(),
+                    start: offset_secs + data.t0 as f32 / 100.0,
+                    end: offset_secs + data.t1 as f32 / 100.0,
+                    probability: data.p,
+                    logprob: data.plog,
+                })
+                .collect();
+            let avg_logprob = if tokens.is_empty() {
+                0.0
+            } else {
+                tokens.iter().map(|t| t.logprob).sum::<f32>() / tokens.len() as f32
+            };
+
+            segments.push(Segment {
+                start: offset_secs + t0 as f32 / 100.0,
+                end: offset_secs + t1 as f32 / 100.0,
+                text,
+                words: group_words(&tokens),
+                avg_logprob,
+                no_speech_prob,
+                temperature,
+                speaker_turn_next: self.diarize && state.full_get_segment_speaker_turn_next(i),
+            });
         }
 
-        Ok(text)
+        segments
     }
 
-    /// Transcribe multiple audio chunks with context continuity
-    /// Uses the end of previous transcription as prompt for next chunk
-    pub fn transcribe_chunks(&self, chunks: &[Vec<f32>]) -> anyhow::Result<String> {
-        if chunks.is_empty() {
-            return Ok(String::new());
+    /// Transcribe (or translate, in translate mode) multiple audio chunks
+    pub fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> anyhow::Result<Transcript> {
+        self.transcribe_chunks_with(chunks, &self.decode)
+    }
+
+    /// Like `transcribe_chunks`, with decoding settings for this call only
+    pub fn transcribe_chunks_with(&self, chunks: &[AudioChunk], options: &DecodeOptions) -> anyhow::Result<Transcript> {
+        options.validate()?;
+        let workers = self.inference.parallel_chunks.clamp(1, chunks.len().max(1));
+        let mut pass = Pass {
+            translate: self.translate,
+            options,
+            threads: (self.inference.threads() / workers).max(1),
+            cancel: Cancel::start(&self.cancel_flag, self.inference.timeout()),
+        };
+
+        let mut transcript = self.run_chunks(chunks, &pass, workers)?;
+        if self.translate && self.keep_original && !pass.cancel.is_cancelled() {
+            pass.translate = false;
+            transcript.original = Some(self.run_chunks(chunks, &pass, workers)?.text());
         }
+        transcript.cancelled = pass.cancel.reason();
+        Ok(transcript)
+    }
 
-        // If only one chunk, use regular transcription
-        if chunks.len() == 1 {
-            return self.transcribe(&chunks[0]);
+    /// Probe and decode one chunk. With `language` unset, it is detected
+    /// from the chunk and returned alongside the segments. A poor decode is
+    /// retried at increasing temperature, up to 1.0. When cancelled, the
+    /// segments whisper.cpp finished before stopping are returned.
+    /// In code-switching mode a chunk detected as the second language is
+    /// decoded in it.
+    fn decode_chunk(
+        &self,
+        state: &mut WhisperState,
+        chunk: &AudioChunk,
+        language: Option<&str>,
+        prompt: &str,
+        pass: &Pass,
+    ) -> anyhow::Result<(Vec<Segment>, Option<DetectedLanguage>)> {
+        // Translation is English already, so there is nothing to switch; in
+        // auto mode `language` is the one detected, not a session language
+        let code_switch =
+            self.code_switch.enabled && self.language != AUTO_LANGUAGE && language.is_some() && !pass.translate;
+        let detect = language.is_none() || code_switch;
+        // The abort callback does not reach the probe's encoder pass
+        if pass.cancel.is_cancelled() {
+            return Ok((Vec::new(), None));
+        }
+        // The probe costs an extra encoder pass; skip it when nothing reads it
+        let mut probe = if detect || self.filter.enabled {
+            self.probe(state, &chunk.samples, detect, pass.threads)?
+        } else {
+            ChunkProbe::default()
+        };
+        let mut language = language
+            .or(probe.language.as_ref().map(|d| d.code))
+            .unwrap_or(DEFAULT_LANGUAGE);
+        let switched = code_switch && self.prefers_second_language(language, &probe.language_probs);
+        if switched {
+            language = &self.code_switch.second_language;
+        }
+        if code_switch {
+            // Detection served the switch only; the session language stays pinned
+            probe.language = None;
         }
 
-        let mut full_text = String::new();
+        let options = pass.options;
+        let mut temperature = options.temperature;
+        let segments = loop {
+            let mut params = self.full_params(language, pass, temperature);
+            params.set_audio_ctx(self.inference.audio_ctx_for(chunk.samples.len()));
+            if !prompt.is_empty() {
+                params.set_initial_prompt(prompt);
+            }
 
-        for chunk in chunks {
-            let mut state = self.ctx.create_state()
-                .map_err(|e| anyhow::anyhow!("Failed to create state: {}", e))?;
-
-            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-            params.set_print_progress(false);
-            params.set_print_special(false);
-            params.set_language(Some("ru"));
-
-            // Build prompt: calibration + previous context
-            let prompt = match (&self.calibration_prompt, full_text.is_empty()) {
-                (Some(cal), true) => cal.clone(),
-                (Some(cal), false) => {
-                    let ctx_start = full_text.len().saturating_sub(100);
-                    format!("{} {}", cal, &full_text[ctx_start..])
-                }
-                (None, false) => {
-                    let ctx_start = full_text.len().saturating_sub(100);
-                    full_text[ctx_start..].to_string()
+            if let Err(e) = state.full(params, &chunk.samples) {
+                // An abort is reported as a failure
+                if !pass.cancel.is_cancelled() {
+                    anyhow::bail!("Failed to run model: {}", e);
                 }
-                (None, true) => String::new(),
-            };
-            
-            if !prompt.is_empty() {
-                params.set_initial_prompt(&prompt);
             }
 
-            state.full(params, chunk)
-                .map_err(|e| anyhow::anyhow!("Failed to run model: {}", e))?;
+            let segments = self.collect_segments(state, chunk.offset_secs, probe.no_speech_prob, temperature);
+            let next = temperature + options.temperature_inc;
+            if pass.cancel.is_cancelled()
+                || options.temperature_inc <= 0.0
+                || next > 1.0 + f32::EPSILON
+                || !options.needs_fallback(&segments, probe.no_speech_prob)
+            {
+                break segments;
+            }
+            temperature = next;
+        };
+
+        let segments = if code_switch && !switched && self.code_switch.redecode_segments {
+            self.redecode_second_language(state, chunk, segments, language, pass)?
+        } else {
+            segments
+        };
+        let segments = self.suppress.clean(segments);
+        let segments = match &self.transliterations {
+            Some(dictionary) => dictionary.apply(segments),
+            None => segments,
+        };
+        Ok((self.filter.apply(segments, options), probe.language))
+    }
+
+    /// Whether detection `probs` favor the second language over `language`
+    fn prefers_second_language(&self, language: &str, probs: &[f32]) -> bool {
+        let prob = |code: &str| {
+            whisper_rs::get_lang_id(code)
+                .and_then(|id| probs.get(id as usize).copied())
+                .unwrap_or(0.0)
+        };
+        language != self.code_switch.second_language
+            && self.code_switch.switches(prob(language), prob(&self.code_switch.second_language))
+    }
+
+    /// Detect the language of each segment on its own audio and decode the
+    /// ones in the second language again with it. Segments shorter than
+    /// `MIN_REDECODE_SECS` are kept, as detection on them is unreliable.
+    fn redecode_second_language(
+        &self,
+        state: &mut WhisperState,
+        chunk: &AudioChunk,
+        segments: Vec<Segment>,
+        language: &str,
+        pass: &Pass,
+    ) -> anyhow::Result<Vec<Segment>> {
+        let second = self.code_switch.second_language.as_str();
+        let mut result = Vec::with_capacity(segments.len());
+
+        for segment in segments {
+            let to_sample = |secs: f32| ((secs - chunk.offset_secs).max(0.0) * SAMPLE_RATE as f32) as usize;
+            let end = to_sample(segment.end).min(chunk.samples.len());
+            let start = to_sample(segment.start).min(end);
+            if pass.cancel.is_cancelled() || end - start < (MIN_REDECODE_SECS * SAMPLE_RATE as f32) as usize {
+                result.push(segment);
+                continue;
+            }
+            let samples = &chunk.samples[start..end];
+
+            state.pcm_to_mel(samples, pass.threads)
+                .map_err(|e| anyhow::anyhow!("Failed to compute mel: {}", e))?;
+            let (_, probs) = state.lang_detect(0, pass.threads)
+                .map_err(|e| anyhow::anyhow!("Failed to detect language: {}", e))?;
+            if !self.prefers_second_language(language, &probs) {
+                result.push(segment);
+                continue;
+            }
 
-            let num_segments = state.full_n_segments().unwrap_or(0);
-            for i in 0..num_segments {
-                if let Ok(segment) = state.full_get_segment_text(i) {
-                    full_text.push_str(&segment);
+            let mut params = self.full_params(second, pass, segment.temperature);
+            params.set_audio_ctx(self.inference.audio_ctx_for(samples.len()));
+            if let Err(e) = state.full(params, samples) {
+                if !pass.cancel.is_cancelled() {
+                    anyhow::bail!("Failed to run model: {}", e);
+                }
+            }
+            // A re-decode cut short is worse than the segment it replaces
+            if pass.cancel.is_cancelled() {
+                result.push(segment);
+                continue;
+            }
+            let offset = chunk.offset_secs + start as f32 / SAMPLE_RATE as f32;
+            let mut redecoded = self.collect_segments(state, offset, segment.no_speech_prob, segment.temperature);
+            match redecoded.last_mut() {
+                Some(last) => {
+                    last.speaker_turn_next |= segment.speaker_turn_next;
+                    result.extend(redecoded);
                 }
+                None => result.push(segment),
             }
         }
+        Ok(result)
+    }
+
+    /// Language to decode with: the configured one, or the detected one in
+    /// auto mode (`None` until detected)
+    fn decode_language<'a>(&'a self, transcript: &Transcript) -> Option<&'a str> {
+        match &transcript.language {
+            Some(detected) => Some(detected.code),
+            None if self.language == AUTO_LANGUAGE => None,
+            None => Some(&self.language),
+        }
+    }
+
+    /// Transcribe multiple audio chunks with context continuity
+    /// Uses the end of previous transcription as prompt for next chunk.
+    /// In auto mode the language is detected on the first chunk only.
+    fn run_chunks(&self, chunks: &[AudioChunk], pass: &Pass, workers: usize) -> anyhow::Result<Transcript> {
+        if workers > 1 {
+            return self.run_chunks_parallel(chunks, pass, workers);
+        }
+
+        let mut transcript = Transcript::default();
+
+        for chunk in chunks {
+            if pass.cancel.is_cancelled() {
+                break;
+            }
+            let mut state = self.acquire_state()?;
+            let prompt = self.context_prompt(pass.translate, &transcript.text());
+            let language = self.decode_language(&transcript);
+
+            let (segments, detected) = self.decode_chunk(&mut state, chunk, language, &prompt, pass)?;
+            if detected.is_some() {
+                transcript.language = detected;
+            }
+            transcript.push_segments(segments);
+        }
+
+        Ok(transcript)
+    }
+
+    /// Transcribe chunks concurrently on `workers` pooled states and
+    /// reassemble them in order. Each chunk gets only the calibration prompt,
+    /// since the previous chunk's text is not known yet.
+    fn run_chunks_parallel(&self, chunks: &[AudioChunk], pass: &Pass, workers: usize) -> anyhow::Result<Transcript> {
+        let mut transcript = Transcript::default();
+
+        // Detect the language once so every chunk decodes with the same one
+        if self.language == AUTO_LANGUAGE && !pass.cancel.is_cancelled() {
+            let mut state = self.acquire_state()?;
+            transcript.language = self.probe(&mut state, &chunks[0].samples, true, pass.threads)?.language;
+        }
+        let language = self.decode_language(&transcript);
+        let prompt = self.context_prompt(pass.translate, "");
+
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<(usize, Vec<Segment>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| -> anyhow::Result<Vec<(usize, Vec<Segment>)>> {
+                        let mut state = self.acquire_state()?;
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(chunk) = chunks.get(i).filter(|_| !pass.cancel.is_cancelled()) else {
+                                break;
+                            };
+                            let (segments, _) = self.decode_chunk(&mut state, chunk, language, &prompt, pass)?;
+                            done.push((i, segments));
+                        }
+                        Ok(done)
+                    })
+                })
+                .collect();
 
-        Ok(full_text)
+            handles
+                .into_iter()
+                .map(|h| h.join().map_err(|_| anyhow::anyhow!("Transcription worker panicked"))?)
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?
+        .into_iter()
+        .flatten()
+        .collect();
+
+        results.sort_by_key(|(i, _)| *i);
+        for (_, segments) in results {
+            transcript.push_segments(segments);
+        }
+        Ok(transcript)
+    }
+}
+
+/// Language and translate mode a model decodes with, given the session's
+/// choice: an English-only model can only transcribe English
+fn model_language(session_language: &str, session_translate: bool, multilingual: bool) -> (&str, bool) {
+    if multilingual {
+        (session_language, session_translate)
+    } else {
+        ("en", false)
     }
 }
 
-extern "C" fn null_log_callback(_level: u32, _message: *const i8, _user_data: *mut c_void) {
-    // Do nothing
-}
\ No newline at end of file
+/// Softmax probability of `logits[index]`
+fn softmax_at(logits: &[f32], index: usize) -> f32 {
+    let Some(&target) = logits.get(index) else {
+        return 0.0;
+    };
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let sum: f32 = logits.iter().map(|l| (l - max).exp()).sum();
+    (target - max).exp() / sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f32, end: f32, text: &str) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            words: Vec::new(),
+            avg_logprob: 0.0,
+            no_speech_prob: 0.0,
+            temperature: 0.0,
+            speaker_turn_next: false,
+        }
+    }
+
+    fn token(bytes: &[u8], start: f32, probability: f32) -> Token {
+        Token { bytes: bytes.to_vec(), start, end: start + 0.1, probability, logprob: probability.ln() }
+    }
+
+    #[test]
+    fn test_group_words() {
+        // "Привет" split inside the second character, then ", мир"
+        let privet = "Привет".as_bytes();
+        let tokens = vec![
+            token(&[b" "[0], privet[0], privet[1], privet[2]], 0.0, 0.9),
+            token(&privet[3..], 0.1, 0.5),
+            token(b",", 0.2, 1.0),
+            token(" мир".as_bytes(), 0.5, 0.2),
+        ];
+        let words = group_words(&tokens);
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Привет,");
+        assert!((words[0].probability - 0.8).abs() < 1e-6);
+        assert!((words[0].end - 0.3).abs() < 1e-6);
+        assert_eq!(words[1].text, "мир");
+        assert!((words[1].start - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fit_audio_ctx() {
+        let fixed = InferenceOptions { audio_ctx: 768, ..Default::default() };
+        assert_eq!(fixed.audio_ctx_for(5 * SAMPLE_RATE), 768);
+
+        let fit = InferenceOptions { fit_audio_ctx: true, ..Default::default() };
+        assert_eq!(fit.audio_ctx_for(5 * SAMPLE_RATE), 250 + 64);
+        assert_eq!(fit.audio_ctx_for(30 * SAMPLE_RATE), FULL_AUDIO_CTX);
+    }
+
+    #[test]
+    fn test_softmax_at() {
+        let p = softmax_at(&[1.0, 1.0, 1.0, 1.0], 2);
+        assert!((p - 0.25).abs() < 1e-6);
+        assert!(softmax_at(&[0.0, 10.0], 0) < 0.001);
+        assert_eq!(softmax_at(&[0.0], 5), 0.0);
+    }
+
+    #[test]
+    fn test_glossary_bias() {
+        // Terms [5, 6, 7] and [5, 8]; ids from 10 up are special tokens
+        let bias = GlossaryBias { sequences: vec![vec![5, 6, 7], vec![5, 8]], boost: 2.0, eot: 10 };
+
+        let mut logits = vec![0.0; 12];
+        bias.apply(&[1, 2], &mut logits);
+        assert_eq!(logits[5], 2.0, "term starts are boosted once");
+        assert_eq!(logits.iter().sum::<f32>(), 2.0);
+
+        // After 5, 6 (with a timestamp token in between) both continuations apply
+        let mut logits = vec![0.0; 12];
+        bias.apply(&[1, 5, 11, 6], &mut logits);
+        assert_eq!(logits[7], 2.0);
+        assert_eq!(logits[5], 2.0);
+        assert_eq!(logits[8], 0.0);
+
+        let mut logits = vec![f32::NEG_INFINITY; 12];
+        bias.apply(&[5], &mut logits);
+        assert_eq!(logits[6], f32::NEG_INFINITY, "suppressed tokens stay suppressed");
+    }
+
+    #[test]
+    fn test_logits_filter_suppresses_tokens() {
+        let bias = GlossaryBias { sequences: vec![vec![5]], boost: 2.0, eot: 10 };
+        let filter = LogitsFilter { glossary: Some(bias), suppressed: vec![3, 5, 99], n_vocab: 12 };
+
+        let mut logits = vec![0.0; 12];
+        filter.apply(&[], &mut logits);
+        assert_eq!(logits[3], f32::NEG_INFINITY);
+        assert_eq!(logits[5], f32::NEG_INFINITY, "suppression wins over the glossary boost");
+        assert_eq!(logits[4], 0.0);
+    }
+
+    #[test]
+    fn test_abort_callback_reads_cancel() {
+        let flag = CancelFlag::default();
+        let cancel = Cancel::start(&flag, None);
+        let data = &cancel as *const Cancel as *mut c_void;
+        // SAFETY: `cancel` outlives the calls, as it does a `full` call
+        unsafe {
+            assert!(!abort_callback(data));
+            flag.cancel();
+            assert!(abort_callback(data));
+            assert!(!abort_callback(std::ptr::null_mut()));
+        }
+    }
+
+    #[test]
+    fn test_model_language_round_trip() {
+        // ggml-small.bin -> ggml-tiny.en.bin -> ggml-small.bin
+        assert_eq!(model_language("ru", true, true), ("ru", true));
+        assert_eq!(model_language("ru", true, false), ("en", false));
+        assert_eq!(model_language("ru", true, true), ("ru", true));
+        assert_eq!(model_language(AUTO_LANGUAGE, false, false), ("en", false));
+    }
+
+    #[test]
+    fn test_needs_fallback() {
+        let options = DecodeOptions::default();
+        assert!(!options.needs_fallback(&[], 0.0));
+
+        let mut clear = segment(0.0, 2.0, " Привет, мир.");
+        clear.avg_logprob = -0.2;
+        assert!(!options.needs_fallback(&[clear], 0.0));
+
+        let looped = segment(0.0, 10.0, &" Я не знаю, что делать.".repeat(8));
+        assert!(options.needs_fallback(&[looped], 0.0));
+
+        let mut unsure = segment(0.0, 2.0, " Привет, мир.");
+        unsure.avg_logprob = -1.4;
+        assert!(options.needs_fallback(std::slice::from_ref(&unsure), 0.1));
+        // Probably silence: leave it to the hallucination filter
+        assert!(!options.needs_fallback(&[unsure], 0.9));
+    }
+
+    #[test]
+    fn test_validate_decoder_counts() {
+        assert!(DecodeOptions { beam_size: 8, best_of: 8, ..Default::default() }.validate().is_ok());
+        assert!(DecodeOptions { beam_size: 10, ..Default::default() }.validate().is_err());
+        assert!(DecodeOptions { best_of: 9, ..Default::default() }.validate().is_err());
+        assert!(DecodeOptions { beam_size: 0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_temperature_inc() {
+        let with_inc = |temperature_inc| DecodeOptions { temperature_inc, ..Default::default() }.validate();
+        assert!(with_inc(0.2).is_ok());
+        assert!(with_inc(0.0).is_ok());
+        assert!(with_inc(0.01).is_err());
+        assert!(with_inc(-0.2).is_err());
+    }
+
+    #[test]
+    fn test_filter_no_speech() {
+        let filter = FilterOptions::default();
+        let decode = DecodeOptions::default();
+        let mut silent = segment(0.0, 2.0, " Угу.");
+        silent.no_speech_prob = 0.9;
+        silent.avg_logprob = -1.5;
+        assert_eq!(filter.check(&silent, None, &decode), Some(Hallucination::NoSpeech));
+
+        // Confident text is kept even when the chunk looked silent
+        silent.avg_logprob = -0.3;
+        assert_eq!(filter.check(&silent, None, &decode), None);
+    }
+
+    #[test]
+    fn test_filter_repetition() {
+        let filter = FilterOptions::default();
+        let decode = DecodeOptions::default();
+        let looped = segment(0.0, 10.0, &" Я не знаю, что делать.".repeat(8));
+        assert_eq!(filter.check(&looped, None, &decode), Some(Hallucination::Repetition));
+
+        let repeated = segment(2.0, 4.0, " Я не знаю, что делать!");
+        assert_eq!(
+            filter.check(&repeated, Some(" я не знаю что делать."), &decode),
+            Some(Hallucination::Repetition)
+        );
+        assert_eq!(filter.check(&repeated, Some(" Вот."), &decode), None);
+    }
+
+    #[test]
+    fn test_filter_blocklist() {
+        let filter = FilterOptions::default();
+        let decode = DecodeOptions::default();
+        let phantom = segment(0.0, 2.0, " ПРОДОЛЖЕНИЕ СЛЕДУЕТ...");
+        assert_eq!(filter.check(&phantom, None, &decode), Some(Hallucination::Blocklisted));
+        let credits = segment(0.0, 2.0, " Субтитры сделал DimaTorzok");
+        assert_eq!(filter.check(&credits, None, &decode), Some(Hallucination::Blocklisted));
+        // Said in the middle of a sentence, or only part of a word
+        let spoken = segment(0.0, 2.0, " Он сказал: спасибо за просмотр презентации, и ушел.");
+        assert_eq!(filter.check(&spoken, None, &decode), None);
+        let partial = segment(0.0, 2.0, " Thanks for watchingdog");
+        assert_eq!(filter.check(&partial, None, &decode), None);
+
+        let segments = vec![segment(0.0, 2.0, " Привет, мир."), phantom];
+        let kept = filter.apply(segments, &decode);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].text, " Привет, мир.");
+
+        let disabled = FilterOptions { enabled: false, ..Default::default() };
+        let segments = vec![segment(0.0, 2.0, " Продолжение следует...")];
+        assert_eq!(disabled.apply(segments, &decode).len(), 1);
+    }
+
+    #[test]
+    fn test_speaker_turns() {
+        let mut first = segment(0.0, 2.0, " Привет.");
+        first.speaker_turn_next = true;
+        let transcript = Transcript {
+            segments: vec![first, segment(2.0, 3.0, " Здравствуйте."), segment(3.0, 4.0, " Как дела?")],
+            ..Default::default()
+        };
+
+        let turns = transcript.turns();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].len(), 1);
+        assert_eq!(turns[1][1].text, " Как дела?");
+        assert!(Transcript::default().turns().is_empty());
+    }
+
+    #[test]
+    fn test_push_segments_drops_overlap() {
+        let mut transcript = Transcript::default();
+        transcript.push_segments(vec![segment(0.0, 3.0, " Раз"), segment(3.0, 6.0, " два")]);
+        // Next chunk starts at 5 s and repeats the tail of the previous one
+        transcript.push_segments(vec![segment(5.0, 6.2, " два"), segment(6.2, 9.0, " три")]);
+
+        assert_eq!(transcript.text(), " Раз два три");
+        assert_eq!(transcript.segments.len(), 3);
+    }
+}
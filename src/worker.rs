@@ -0,0 +1,618 @@
+//! Background transcription.
+//! A worker thread owns the model and takes jobs from a queue in order:
+//! recordings, streamed audio and settings changes. The UI stays responsive,
+//! so the next recording can start while earlier ones are still being
+//! transcribed, and results are printed in recording order.
+
+use crossbeam_channel::{unbounded, Sender};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::audio_processor::{AudioProcessor, SAMPLE_RATE};
+use crate::cancel::{CancelFlag, CancelReason};
+use crate::config::{self, Config};
+use crate::export;
+use crate::models;
+use crate::prompt::SessionContext;
+use crate::streaming::{LiveTranscriber, StreamEvent};
+use crate::transcriber::Transcriber;
+use crate::ui;
+use crate::whisper::{DecodeOptions, DecodeStrategy, Transcript, WhisperModel};
+
+/// Model settings the worker changes between jobs
+pub trait SessionModel: Transcriber {
+    fn model_path(&self) -> &Path;
+    fn switch_model(&mut self, model_path: &Path) -> anyhow::Result<()>;
+    fn cancel_flag(&self) -> CancelFlag;
+    fn set_session_context(&mut self, text: &str);
+    fn language(&self) -> &str;
+    fn translate(&self) -> bool;
+    fn set_translate(&mut self, translate: bool) -> anyhow::Result<()>;
+    fn decode_options(&self) -> &DecodeOptions;
+    fn set_decode_options(&mut self, options: DecodeOptions) -> anyhow::Result<()>;
+}
+
+impl SessionModel for WhisperModel {
+    fn model_path(&self) -> &Path {
+        WhisperModel::model_path(self)
+    }
+
+    fn switch_model(&mut self, model_path: &Path) -> anyhow::Result<()> {
+        WhisperModel::switch_model(self, model_path)
+    }
+
+    fn cancel_flag(&self) -> CancelFlag {
+        WhisperModel::cancel_flag(self)
+    }
+
+    fn set_session_context(&mut self, text: &str) {
+        WhisperModel::set_session_context(self, text)
+    }
+
+    fn language(&self) -> &str {
+        WhisperModel::language(self)
+    }
+
+    fn translate(&self) -> bool {
+        WhisperModel::translate(self)
+    }
+
+    fn set_translate(&mut self, translate: bool) -> anyhow::Result<()> {
+        WhisperModel::set_translate(self, translate)
+    }
+
+    fn decode_options(&self) -> &DecodeOptions {
+        WhisperModel::decode_options(self)
+    }
+
+    fn set_decode_options(&mut self, options: DecodeOptions) -> anyhow::Result<()> {
+        WhisperModel::set_decode_options(self, options)
+    }
+}
+
+/// Work for the transcription thread, done in the order sent
+pub enum Job {
+    /// Transcribe a finished recording
+    Transcribe(Vec<f32>),
+    /// Streaming mode: a recording started
+    StreamStart,
+    /// Streaming mode: audio captured since the last tick
+    StreamAudio(Vec<f32>),
+    /// Streaming mode: the recording stopped, with its last audio
+    StreamEnd(Vec<f32>),
+    /// Transcribe the last recording again with the current settings
+    Rerun,
+    /// Switch translate-to-English mode
+    ToggleTranslate,
+    /// Load the next installed model
+    SwitchModel,
+    /// Switch between greedy decoding and beam search
+    ToggleBeamSearch,
+    /// Forget the session context
+    ResetContext,
+}
+
+impl Job {
+    /// Whether the job transcribes a whole recording
+    fn is_transcription(&self) -> bool {
+        matches!(self, Job::Transcribe(_) | Job::StreamEnd(_) | Job::Rerun)
+    }
+}
+
+/// Count of queued and running transcriptions, shared with the UI
+#[derive(Clone, Default)]
+pub struct Backlog(Arc<AtomicUsize>);
+
+impl Backlog {
+    pub fn len(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Handle to the transcription thread
+pub struct Worker {
+    jobs: Sender<Job>,
+    backlog: Backlog,
+    cancel: CancelFlag,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    /// Start the thread; it runs until `finish` is called
+    pub fn spawn<M: SessionModel + Send + 'static>(model: M, config: Config) -> Self {
+        let (jobs, queue) = unbounded::<Job>();
+        let backlog = Backlog::default();
+        let cancel = model.cancel_flag();
+
+        let handle = thread::spawn({
+            let backlog = backlog.clone();
+            let cancel = cancel.clone();
+            move || {
+                let context = SessionContext::new(config.prompt.session_utterances);
+                let mut session = Session {
+                    model,
+                    config,
+                    live: None,
+                    last_audio: Vec::new(),
+                    context,
+                    last_added: 0,
+                    streamed: Transcript::default(),
+                };
+                for job in queue {
+                    let counted = job.is_transcription();
+                    // ESC cancels the job taken up last, even before it
+                    // reaches the model
+                    cancel.reset();
+                    session.run(job);
+                    if counted {
+                        backlog.0.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }
+            }
+        });
+        Self { jobs, backlog, cancel, handle }
+    }
+
+    /// Queue a job
+    pub fn send(&self, job: Job) {
+        if job.is_transcription() {
+            self.backlog.0.fetch_add(1, Ordering::SeqCst);
+        }
+        // The thread only stops once `jobs` is dropped
+        self.jobs.send(job).ok();
+    }
+
+    /// Transcriptions waiting or running
+    pub fn backlog(&self) -> Backlog {
+        self.backlog.clone()
+    }
+
+    /// Stop the running transcription; queued ones still run
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Close the queue and wait for the queued jobs to finish
+    pub fn finish(self) {
+        let pending = self.backlog.len();
+        if pending > 0 {
+            print!("\r⏳ Finishing {} queued transcription(s)...\r\n", pending);
+            io::stdout().flush().unwrap();
+        }
+        drop(self.jobs);
+        if self.handle.join().is_err() {
+            log::error!("Transcription thread panicked");
+        }
+    }
+}
+
+/// Model and per-session state owned by the worker thread
+struct Session<M> {
+    model: M,
+    config: Config,
+    /// Live transcriber, present while recording in streaming mode
+    live: Option<LiveTranscriber>,
+    /// Last recording, kept for re-running it with other settings
+    last_audio: Vec<f32>,
+    /// Recent transcripts prompting the next recording
+    context: SessionContext,
+    /// Entries of `context` added by the last recording: one per commit
+    /// in streaming mode, none if it had no text
+    last_added: usize,
+    /// Commits of the streamed recording, exported when it ends
+    streamed: Transcript,
+}
+
+impl<M: SessionModel> Session<M> {
+    fn run(&mut self, job: Job) {
+        match job {
+            Job::Transcribe(audio) => {
+                self.last_audio = audio;
+                self.last_added = 0;
+                if let Some(transcript) = self.transcribe_last(true) {
+                    self.remember(&transcript);
+                }
+            }
+            Job::StreamStart => {
+                self.last_audio.clear();
+                self.last_added = 0;
+                self.streamed = Transcript::default();
+                self.live = Some(LiveTranscriber::new(self.config.stream.clone()));
+            }
+            Job::StreamAudio(audio) => {
+                let Some(live) = self.live.as_mut() else { return };
+                self.last_audio.extend_from_slice(&audio);
+                live.push(&audio);
+                match live.tick(&self.model) {
+                    Ok(Some(StreamEvent::Partial(text))) => ui::show_partial(&text),
+                    Ok(Some(StreamEvent::Commit(transcript))) => self.commit(transcript),
+                    Ok(None) => {}
+                    Err(e) => eprint!("\r❌ Error: {}\r\n", e),
+                }
+            }
+            Job::StreamEnd(audio) => {
+                let Some(mut live) = self.live.take() else { return };
+                // Only the uncommitted tail is left to transcribe
+                self.last_audio.extend_from_slice(&audio);
+                live.push(&audio);
+                match live.commit(&self.model) {
+                    Ok(Some(transcript)) => self.commit(transcript),
+                    Ok(None) => print!("\r\x1b[2K"),
+                    Err(e) => eprint!("\r❌ Error: {}\r\n", e),
+                }
+                let streamed = std::mem::take(&mut self.streamed);
+                if !streamed.segments.is_empty() {
+                    self.export(&streamed);
+                }
+            }
+            Job::Rerun => {
+                if self.last_audio.is_empty() {
+                    print!("\r⚠️  Nothing recorded yet.\r\n");
+                } else {
+                    print!(
+                        "\r🔁 Re-running with {} ({:?})\r\n",
+                        self.model.model_path().display(),
+                        self.model.decode_options().strategy
+                    );
+                    // Prompt with the context the recording had, not its own text
+                    let own = self.context.pop_last(self.last_added);
+                    self.last_added = 0;
+                    self.model.set_session_context(&self.context.text());
+                    match self.transcribe_last(false) {
+                        Some(transcript) if transcript.cancelled.is_none() => self.remember(&transcript),
+                        // Keep the earlier text rather than a partial one
+                        _ => {
+                            for text in &own {
+                                self.context.push(text);
+                            }
+                            self.last_added = own.len();
+                            self.model.set_session_context(&self.context.text());
+                        }
+                    }
+                }
+            }
+            Job::ToggleTranslate => {
+                let translate = !self.model.translate();
+                match self.model.set_translate(translate) {
+                    Ok(()) => print!("\r🌍 Translate to English: {}\r\n", if translate { "on" } else { "off" }),
+                    Err(e) => print!("\r❌ {}\r\n", e),
+                }
+            }
+            Job::SwitchModel => self.switch_model(),
+            Job::ResetContext => {
+                self.context.clear();
+                self.last_added = 0;
+                self.model.set_session_context("");
+                print!("\r🧹 Session context cleared\r\n");
+            }
+            Job::ToggleBeamSearch => {
+                let mut options = self.model.decode_options().clone();
+                options.strategy = match options.strategy {
+                    DecodeStrategy::Greedy => DecodeStrategy::BeamSearch,
+                    DecodeStrategy::BeamSearch => DecodeStrategy::Greedy,
+                };
+                let strategy = options.strategy;
+                match self.model.set_decode_options(options) {
+                    Ok(()) => print!("\r🎛  Decoding: {:?}\r\n", strategy),
+                    Err(e) => print!("\r❌ {}\r\n", e),
+                }
+            }
+        }
+        io::stdout().flush().unwrap();
+    }
+
+    /// Add a transcript to the session context, in the spoken language: the
+    /// original when translating, nothing if it was not kept
+    fn remember(&mut self, transcript: &Transcript) {
+        let text = match (&transcript.original, self.model.translate()) {
+            (Some(original), _) => original.clone(),
+            (None, false) => transcript.text(),
+            (None, true) => return,
+        };
+        if self.context.push(&text) {
+            self.last_added += 1;
+        }
+        self.model.set_session_context(&self.context.text());
+    }
+
+    /// Print a streamed commit and keep it for the recording's export
+    fn commit(&mut self, mut transcript: Transcript) {
+        print!("\r\x1b[2K📝 {}\r\n", ui::render_transcript(&transcript));
+        self.remember(&transcript);
+        if self.streamed.language.is_none() {
+            self.streamed.language = transcript.language.take();
+        }
+        self.streamed.segments.append(&mut transcript.segments);
+    }
+
+    /// Save a transcript to the configured export directory, if any
+    fn export(&self, transcript: &Transcript) {
+        if let Some(dir) = &self.config.export_dir {
+            match export::save(dir, transcript) {
+                Ok(path) => print!("\r💾 Saved {}\r\n", path.display()),
+                Err(e) => print!("\r❌ Export failed: {}\r\n", e),
+            }
+        }
+    }
+
+    fn switch_model(&mut self) {
+        let models = config::installed_models();
+        let Some(next) = config::next_model(&models, self.model.model_path()).cloned() else {
+            print!("\r⚠️  No models found in ./models or the data dir\r\n");
+            return;
+        };
+        print!("\r⏳ Loading {}...\r\n", next.display());
+        io::stdout().flush().unwrap();
+        let verified = if self.config.verify_models { models::verify_checksum(&next) } else { Ok(()) };
+        match verified.and_then(|_| self.model.switch_model(&next)) {
+            Ok(()) => print!("\r🔄 Model: {} (language: {})\r\n", next.display(), self.model.language()),
+            Err(e) => print!("\r❌ {}\r\n", e),
+        }
+    }
+
+    /// Transcribe the last recording and print the result; with `export`,
+    /// also save it to the configured export directory
+    fn transcribe_last(&self, export: bool) -> Option<Transcript> {
+        let audio = &self.last_audio;
+        if audio.is_empty() {
+            print!("\r⚠️  No audio recorded.\r\n");
+            return None;
+        }
+
+        // Process audio: trim silence, normalize, chunk
+        let processor = AudioProcessor::default();
+        let chunks = processor.process(audio);
+        if chunks.is_empty() {
+            print!("\r⚠️  No speech detected.\r\n");
+            return None;
+        }
+
+        print!("\r⏳ Transcribing {} chunk(s)... (ESC to cancel)\r\n", chunks.len());
+        io::stdout().flush().unwrap();
+
+        // Transcribe using chunked method with context
+        let started = Instant::now();
+        let transcript = match self.model.transcribe_chunks(&chunks) {
+            Ok(transcript) => transcript,
+            Err(e) => {
+                log::error!("Transcription failed: {:#}", e);
+                eprint!("\r❌ Error: {}\r\n", e);
+                return None;
+            }
+        };
+        let elapsed = started.elapsed().as_secs_f32();
+        let duration = audio.len() as f32 / SAMPLE_RATE as f32;
+
+        match transcript.cancelled {
+            Some(CancelReason::Requested) => print!("\r⛔ Cancelled, showing the text decoded so far\r\n"),
+            Some(CancelReason::Timeout) => {
+                log::warn!("Transcription of {:.1}s of audio timed out after {:.1}s", duration, elapsed);
+                print!("\r⛔ Timed out after {:.1}s, showing the text decoded so far\r\n", elapsed);
+            }
+            None => {}
+        }
+        if let Some(original) = &transcript.original {
+            print!("\r🗣  ORIGINAL: {}\r\n", original.trim());
+        }
+        print!("\r📝 RESULT: {}\r\n", ui::render_transcript(&transcript));
+        if let Some(unsure) = ui::render_unsure_words(&transcript) {
+            print!("\r🔍 Unsure: {}\r\n", unsure);
+        }
+        if transcript.max_temperature() > self.model.decode_options().temperature {
+            print!("\r🌡  Re-decoded at temperature {:.1}\r\n", transcript.max_temperature());
+        }
+        if let Some(lang) = &transcript.language {
+            print!("\r🌐 Detected: {} ({:.0}%)\r\n", lang.code, lang.probability * 100.0);
+        }
+        print!(
+            "\r⏱  {:.2}s for {:.1}s of audio (RTF {:.2})\r\n",
+            elapsed,
+            duration,
+            elapsed / duration
+        );
+        if export {
+            self.export(&transcript);
+        }
+        Some(transcript)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::AudioChunk;
+    use crate::cancel::Cancel;
+    use crate::transcriber::fake::ScriptedTranscriber;
+    use crossbeam_channel::{bounded, Receiver};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Scripted model recording the session contexts it was given and why
+    /// each transcription stopped early
+    struct FakeModel {
+        transcriber: ScriptedTranscriber,
+        path: PathBuf,
+        translate: bool,
+        decode: DecodeOptions,
+        cancel: CancelFlag,
+        contexts: Arc<Mutex<Vec<String>>>,
+        cancelled: Arc<Mutex<Vec<Option<CancelReason>>>>,
+        /// Signals each transcription starting, then waits to be released
+        gate: Option<(Sender<()>, Receiver<()>)>,
+    }
+
+    impl FakeModel {
+        fn new(texts: &[&str]) -> Self {
+            Self {
+                transcriber: ScriptedTranscriber::new(texts),
+                path: PathBuf::from("fake.bin"),
+                translate: false,
+                decode: DecodeOptions::default(),
+                cancel: CancelFlag::default(),
+                contexts: Arc::default(),
+                cancelled: Arc::default(),
+                gate: None,
+            }
+        }
+    }
+
+    impl Transcriber for FakeModel {
+        fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> anyhow::Result<Transcript> {
+            let cancel = Cancel::start(&self.cancel, None);
+            if let Some((started, release)) = &self.gate {
+                started.send(()).unwrap();
+                release.recv().unwrap();
+            }
+            let mut transcript = self.transcriber.transcribe_chunks(chunks)?;
+            transcript.cancelled = cancel.reason();
+            self.cancelled.lock().unwrap().push(transcript.cancelled);
+            Ok(transcript)
+        }
+    }
+
+    impl SessionModel for FakeModel {
+        fn model_path(&self) -> &Path {
+            &self.path
+        }
+
+        fn switch_model(&mut self, model_path: &Path) -> anyhow::Result<()> {
+            self.path = model_path.to_path_buf();
+            Ok(())
+        }
+
+        fn cancel_flag(&self) -> CancelFlag {
+            self.cancel.clone()
+        }
+
+        fn set_session_context(&mut self, text: &str) {
+            self.contexts.lock().unwrap().push(text.to_string());
+        }
+
+        fn language(&self) -> &str {
+            "ru"
+        }
+
+        fn translate(&self) -> bool {
+            self.translate
+        }
+
+        fn set_translate(&mut self, translate: bool) -> anyhow::Result<()> {
+            self.translate = translate;
+            Ok(())
+        }
+
+        fn decode_options(&self) -> &DecodeOptions {
+            &self.decode
+        }
+
+        fn set_decode_options(&mut self, options: DecodeOptions) -> anyhow::Result<()> {
+            self.decode = options;
+            Ok(())
+        }
+    }
+
+    fn tone(secs: usize) -> Vec<f32> {
+        (0..secs * SAMPLE_RATE).map(|i| (i as f32 * 0.05).sin() * 0.5).collect()
+    }
+
+    /// Model whose transcriptions wait for the test, with the channels to
+    /// see them start and to release them
+    fn gated(texts: &[&str]) -> (FakeModel, Receiver<()>, Sender<()>) {
+        let (started_tx, started) = bounded(0);
+        let (release, release_rx) = bounded(0);
+        let model = FakeModel { gate: Some((started_tx, release_rx)), ..FakeModel::new(texts) };
+        (model, started, release)
+    }
+
+    #[test]
+    fn test_results_in_recording_order() {
+        let model = FakeModel::new(&[" Раз.", " Два.", " Три."]);
+        let contexts = model.contexts.clone();
+        let worker = Worker::spawn(model, Config::default());
+        for _ in 0..3 {
+            worker.send(Job::Transcribe(tone(2)));
+        }
+        worker.finish();
+
+        assert_eq!(contexts.lock().unwrap().last().unwrap(), "Раз. Два. Три.");
+    }
+
+    #[test]
+    fn test_backlog_counts_transcriptions() {
+        let (model, started, release) = gated(&[" Раз.", " Два."]);
+        let worker = Worker::spawn(model, Config::default());
+        let backlog = worker.backlog();
+        worker.send(Job::Transcribe(tone(2)));
+        worker.send(Job::ToggleTranslate);
+        worker.send(Job::Transcribe(tone(2)));
+        assert_eq!(backlog.len(), 2, "settings changes are not counted");
+
+        for _ in 0..2 {
+            started.recv().unwrap();
+            release.send(()).unwrap();
+        }
+        worker.finish();
+        assert!(backlog.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_leaves_queued_jobs() {
+        let (model, started, release) = gated(&[" Раз", " Два."]);
+        let (contexts, cancelled) = (model.contexts.clone(), model.cancelled.clone());
+        let worker = Worker::spawn(model, Config::default());
+        worker.send(Job::Transcribe(tone(2)));
+        worker.send(Job::Transcribe(tone(2)));
+
+        // Cancel the first while the second waits
+        started.recv().unwrap();
+        worker.cancel();
+        release.send(()).unwrap();
+        started.recv().unwrap();
+        release.send(()).unwrap();
+        worker.finish();
+
+        // The cancelled one keeps its partial text, the queued one ran in full
+        assert_eq!(*cancelled.lock().unwrap(), [Some(CancelReason::Requested), None]);
+        assert_eq!(contexts.lock().unwrap().last().unwrap(), "Раз Два.");
+    }
+
+    #[test]
+    fn test_rerun_replaces_only_its_own_context() {
+        let model = FakeModel::new(&[" Раз.", " ", " Два."]);
+        let contexts = model.contexts.clone();
+        let worker = Worker::spawn(model, Config::default());
+        worker.send(Job::Transcribe(tone(2)));
+        // A recording without text adds nothing for the re-run to remove
+        worker.send(Job::Transcribe(tone(2)));
+        worker.send(Job::Rerun);
+        worker.finish();
+
+        assert_eq!(contexts.lock().unwrap().last().unwrap(), "Раз. Два.");
+    }
+
+    #[test]
+    fn test_stream_end_exports_recording() {
+        let dir = std::env::temp_dir().join(format!("voice-agent-stream-export-{}", std::process::id()));
+        let config = Config { export_dir: Some(dir.clone()), ..Config::default() };
+        let worker = Worker::spawn(FakeModel::new(&[" Раз.", " Два."]), config);
+        worker.send(Job::StreamStart);
+        // A forced commit mid-recording, then the tail on stop
+        worker.send(Job::StreamAudio(tone(31)));
+        worker.send(Job::StreamEnd(tone(1)));
+        worker.finish();
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+        let text = files.iter().find(|p| p.extension().is_some_and(|e| e == "txt")).map(std::fs::read_to_string);
+        std::fs::remove_dir_all(&dir).ok();
+        let text = text.expect("a text export").unwrap();
+        assert!(text.contains("Раз.") && text.contains("Два."), "{}", text);
+    }
+}